@@ -1,19 +1,16 @@
-use std::{
-    collections::HashMap,
-    fs::File,
-    io::{Read, Write},
-};
-
 use dwldutil::Downloader;
 use mcd::{
     api::ApiClientUtil,
-    command::{self, Command},
+    auth::offline::OfflineAccount,
     file::fetch_client,
-    java::JavaUtil,
+    java::{JavaSource, JavaUtil},
+    jvm::{GcProfile, HeapSize, JvmPreset},
+    launch::{InstallLayout, LaunchContext},
     libs::LibsUtil,
+    process::ProcessOptions,
     resource::ResourceUtil,
 };
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 use tracing_subscriber::EnvFilter;
 
 fn main() -> anyhow::Result<()> {
@@ -32,9 +29,19 @@ fn main() -> anyhow::Result<()> {
 
     dbg!(&client.java());
 
-    match java.fetch(client.java(), "./test/java") {
-        Ok(f) => files.push(f),
-        Err(mcd::errors::FetchError::PathAlredyExist(_)) => {}
+    // one directory per version, a profile switch doesn't touch the other runtimes
+    let java_directory = format!("./test/java/{}", client.java());
+    let mut java_path = format!(
+        "{}/{}/bin/java",
+        java_directory,
+        java.id_of(client.java()).unwrap_or_default()
+    );
+    match java.prepare(client.java(), &java_directory) {
+        Ok(JavaSource::Installed(installed)) => {
+            client.check_java(installed.major)?;
+            java_path = installed.executable.to_string_lossy().into_owned()
+        }
+        Ok(JavaSource::Download(mut f)) => files.append(&mut f),
         Err(e) => error!("{}", e),
     };
     match fetch_client(&client, "test/game.jar") {
@@ -42,7 +49,7 @@ fn main() -> anyhow::Result<()> {
         Err(mcd::errors::FetchError::PathAlredyExist(_)) => {}
         Err(e) => error!("{}", e),
     }
-    let mut classpath = match libs.fetch("./test/libraries", "./test/bin", &client) {
+    let classpath = match libs.fetch("./test/libraries", "./test/bin", &client) {
         Ok((mut f, classpath)) => {
             debug!("LIBS: {}", f.len());
             files.append(&mut f);
@@ -54,64 +61,48 @@ fn main() -> anyhow::Result<()> {
             Vec::new()
         }
     };
-    //classpath.push(String::from("test/game.jar"));
-
-    //let mut file = File::create_new("./classpath")?;
-    //file.write_all(classpath.join(":").as_bytes());
-    //let mut file = File::open("classpath")?;
-    //let mut classpath = String::new();
-    //file.read_to_string(&mut classpath)?;
-
-    //let index = resources.index_of(&client, "./test/index.json")?;
-    //match resources.fetch(&index, "./test/assets") {
-    //    Ok(mut f) => files.append(&mut f),
-    //                    Err(mcd::errors::FetchError::PathAlredyExist(_)) => {}
-    //                    Err(e) => error!("{}", e),
-    //}
+    let layout = InstallLayout::new("./test");
+    let index = resources.index_of(&client, &layout)?;
+    match resources.fetch(&index, &layout) {
+        Ok(mut f) => files.append(&mut f),
+        Err(mcd::errors::FetchError::PathAlredyExist(_)) => {}
+        Err(e) => error!("{}", e),
+    }
     Downloader::<dwldutil::indicator::indicatif::Indicatif>::new()
         .with_max_redirections(30)
         .with_max_concurrent_downloads(1)
         .with_files(files)
         .start();
 
-    // let mut data = HashMap::new();
+    let context = LaunchContext::new(&client, layout, classpath)
+        .with_client_jar("test/game.jar")
+        .with_natives_directory("./test/bin")
+        .with_assets(&index)
+        .with_session(OfflineAccount::new("ddd")?.session())
+        .with_window(1280, 720);
 
-    // data.insert("natives_directory".to_owned(), "/home/kristian/Documentos/proyects/mcdu/mcd/test/bin".to_owned());
-    // data.insert("launcher_name".to_owned(), "theseus".to_owned());
-    // data.insert("launcher_version".to_owned(), "0.9.5".to_owned());
-    // data.insert(
-    //     "classpath".to_owned(),
-    //     format!("{}", classpath.join(":")),
-    // );
-    // data.insert("main_class".to_owned(), client.main_class.to_owned());
-    // data.insert("auth_player_name".to_owned(), "ddd".to_owned());
-    // data.insert("version_name".to_owned(), "1.21.5".to_owned());
-    // data.insert("game_directory".to_owned(), "/home/kristian/.local/share/ModrinthApp/profiles/dd".to_owned());
-    // data.insert("assets_root".to_owned(), "/home/kristian/.local/share/ModrinthApp/meta/assets".to_owned());
-    // data.insert(
-    //     "game_assets".to_owned(),
-    //     "./test/assets/virtual/legacy/".to_owned(),
-    // );
-    // data.insert("assets_index_name".to_owned(), client.assets.clone());
-    // data.insert("auth_uuid".to_owned(), "d3ae2061edcd4cdebe44af586ca1a1a9".to_owned());
-    // data.insert("auth_access_token".to_owned(), "0".to_owned());
-    // data.insert("clientid".to_owned(), "c4502edb-87c6-40cb-b595-64a280cf8906".to_owned());
-    // data.insert("auth_xuid".to_owned(), "90".to_owned());
-    // data.insert("user_type".to_owned(), "msa".to_owned());
-    // data.insert("version_type".to_owned(), "vanilla".to_owned());
-    // data.insert("user_properties".to_owned(), "".to_owned());
-    // data.insert("library_directory".to_owned(), "/home/kristian/Documentos/proyects/mcdu/mcd/test/libraries".to_owned());
-    // data.insert("classpath_separator".to_owned(), ":".to_owned());
-    // data.insert("log_file".to_owned(), "test/log4j2.xml".to_owned());
-    // data.insert("file".to_owned(), "test/1.21.5.jar".to_owned());
-
-    // let (game, jvm) = command::build_args(&client, HashMap::new());
+    let (game, jvm) = context.args(&client);
+    for key in context.unfilled(&game, &jvm) {
+        warn!("Placeholder {} is not filled", key);
+    }
 
-    // Command::from_args(game, jvm, data).execute(
-    //     "/home/kristian/.local/share/ModrinthApp/meta/java_versions/zulu21.42.19-ca-jre21.0.7-linux_x64/bin/java".to_owned(),
-    //     vec![],
-    //     client.java()
-    // )?;
+    let process = context
+        .command(game, jvm)
+        .launch()?
+        .with_preset(
+            &JvmPreset::new()
+                .with_max_heap(HeapSize::Mebibytes(2048))
+                .with_gc(GcProfile::G1Tuned),
+            client.java(),
+        )?
+        .with_argfile("./test/launch.args", client.java())
+        .spawn(&ProcessOptions::new(&java_path))?;
+    debug!("PID {}", process.pid());
+    for line in process.output() {
+        println!("{}", line.line());
+    }
+    let status = process.wait()?;
+    debug!("EXIT {}", status);
 
     Ok(())
 }
@@ -1,12 +1,27 @@
 use std::collections::HashMap;
 use serde::Deserialize;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 pub struct AssetsObject {
     pub hash: String,
     pub size: u64
 }
-#[derive(Deserialize)]
+impl AssetsObject {
+    /// Path of the object inside `objects/`
+    pub fn path(&self) -> String {
+        format!("{}/{}", &self.hash[..2], self.hash)
+    }
+}
+#[derive(Deserialize, Clone)]
 pub struct Assets {
+    /// Id of the index, set by `ResourceUtil::index_of`
+    #[serde(skip)]
+    pub id: String,
+    /// Assets are also copied by key into `virtual/<id>`, used by legacy versions
+    #[serde(default, rename = "virtual")]
+    pub is_virtual: bool,
+    /// Assets are also copied by key into the `resources/` game directory, used before 1.6
+    #[serde(default)]
+    pub map_to_resources: bool,
     pub objects: HashMap<String, AssetsObject>
 }
@@ -1,7 +1,9 @@
 use serde::Deserialize;
 use std::{collections::HashMap};
 
-use crate::os::system::OperatingSystem;
+use tracing::{debug, warn};
+
+use crate::{errors::JavaError, os::system::OperatingSystem};
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct Client {
@@ -108,11 +110,12 @@ pub struct LibraryDownloadsArtifacts {
     pub(crate) url: String,
 }
 
+/// Os condition of a rule, only `name` is evaluated by `Rule::allow`
 #[derive(Debug, Deserialize, Clone, Default)]
 pub struct RuleOs {
     pub(crate) name: Option<String>,
-    pub(crate) arch: Option<String>,
-    pub(crate) version: Option<String>,
+    pub arch: Option<String>,
+    pub version: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -145,21 +148,23 @@ pub struct Library {
     #[serde(default)]
     pub(crate) url: String,
     pub natives: Option<LibraryNatives>,
+    /// Checksums of maven libraries without `downloads`, not verified by `LibsUtil`
     #[serde(default)]
-    md5: String,
+    pub md5: String,
     #[serde(default)]
-    sha1: String,
+    pub sha1: String,
     #[serde(default)]
-    sha256: String,
+    pub sha256: String,
     #[serde(default)]
-    sha521: String,
+    pub sha521: String,
     #[serde(default)]
-    size: usize,
-    pub(crate) extract: Option<LibraryExtract>,
+    pub size: usize,
+    pub extract: Option<LibraryExtract>,
 }
+/// Paths the version excludes when extracting the natives, e.g. `META-INF/`
 #[derive(Deserialize, Debug, Clone)]
 pub struct LibraryExtract {
-    exclude: Vec<String>,
+    pub exclude: Vec<String>,
 }
 #[derive(Deserialize, Debug, Default, Clone)]
 pub struct LibraryNatives {
@@ -187,7 +192,137 @@ pub struct LogSettingsClientFile {
     pub url: String,
 }
 impl Client {
+    /// Java major version of the client, inferred for old jsons without `javaVersion`
     pub fn java(&self) -> usize {
-        self.java_version.major_version as usize
+        match self.java_version.major_version {
+            0 => infer_java_major(&self.id, &self.release_time),
+            major => major as usize,
+        }
+    }
+    /// Errors when the Java is older than required, warns when an old version gets a newer one
+    pub fn check_java(&self, major: usize) -> Result<(), JavaError> {
+        let required = self.java();
+        if major < required {
+            return Err(JavaError::VersionMismatch(required, major));
+        }
+        if major > required && required < 16 {
+            warn!(
+                "{} REQUIRES JAVA {}, JAVA {} MAY BREAK IT",
+                self.id, required, major
+            );
+        }
+        Ok(())
+    }
+    /// Whether a game argument rule of the version checks the feature
+    pub fn declares_feature(&self, name: &str) -> bool {
+        let Some(arguments) = &self.arguments else {
+            return false;
+        };
+        arguments.game.iter().any(|arg| match arg {
+            ArgumentValue::Complex(complex) => complex.rules.iter().any(|rule| {
+                rule.features
+                    .as_ref()
+                    .is_some_and(|features| features.contains_key(name))
+            }),
+            ArgumentValue::Plain(_) => false,
+        })
+    }
+}
+
+/// Java major of a version by release date, falling back on the id when the date is missing:
+/// 8 before 1.17 (21w19a), 16 for 1.17, 17 until 1.20.4 and 21 from 1.20.5 (24w14a)
+pub fn infer_java_major(id: &str, release_time: &str) -> usize {
+    let major = match release_time.get(..10) {
+        Some(date) if date.as_bytes()[4] == b'-' => match date {
+            date if date < "2021-05-12" => 8,
+            date if date < "2021-11-16" => 16,
+            date if date < "2024-04-03" => 17,
+            _ => 21,
+        },
+        _ => infer_java_major_from_id(id),
+    };
+    debug!("INFERRED JAVA {} FOR {}", major, id);
+    major
+}
+fn infer_java_major_from_id(id: &str) -> usize {
+    // snapshots as (year, week)
+    if let Some((year, rest)) = id.split_once('w')
+        && let (Ok(year), Ok(week)) = (
+            year.parse::<u32>(),
+            rest.get(..2).unwrap_or("").parse::<u32>(),
+        )
+    {
+        return match (year, week) {
+            snapshot if snapshot < (21, 19) => 8,
+            snapshot if snapshot < (21, 45) => 16,
+            snapshot if snapshot < (24, 14) => 17,
+            _ => 21,
+        };
+    }
+    let mut parts = id
+        .split(['.', '-', ' '])
+        .map(|part| part.parse::<u32>().unwrap_or(0));
+    let release = (
+        parts.next().unwrap_or(0),
+        parts.next().unwrap_or(0),
+        parts.next().unwrap_or(0),
+    );
+    match release {
+        (1, minor, _) if minor < 17 => 8,
+        (1, 17, _) => 16,
+        (1, minor, _) if minor < 20 => 17,
+        (1, 20, patch) if patch <= 4 => 17,
+        // ids that aren't releases, e.g. old alphas and betas
+        (0, _, _) => 8,
+        _ => 21,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn java_major_by_release_date() {
+        assert_eq!(infer_java_major("1.16.5", "2021-01-14T16:05:32+00:00"), 8);
+        assert_eq!(infer_java_major("21w19a", "2021-05-12T11:19:15+00:00"), 16);
+        assert_eq!(infer_java_major("1.17.1", "2021-07-06T12:01:34+00:00"), 16);
+        assert_eq!(infer_java_major("21w44a", "2021-11-03T14:38:56+00:00"), 16);
+        assert_eq!(infer_java_major("1.18", "2021-11-30T09:16:29+00:00"), 17);
+        assert_eq!(infer_java_major("1.20.4", "2023-12-07T12:56:20+00:00"), 17);
+        assert_eq!(infer_java_major("24w14a", "2024-04-03T12:34:56+00:00"), 21);
+        assert_eq!(infer_java_major("1.21.1", "2024-08-08T12:24:45+00:00"), 21);
+    }
+
+    #[test]
+    fn java_major_by_id_without_date() {
+        assert_eq!(infer_java_major("1.8.9", ""), 8);
+        assert_eq!(infer_java_major("1.16.5-forge-36.2.39", ""), 8);
+        assert_eq!(infer_java_major("1.17", ""), 16);
+        assert_eq!(infer_java_major("1.19.2", ""), 17);
+        assert_eq!(infer_java_major("1.20.4", ""), 17);
+        assert_eq!(infer_java_major("1.20.5", ""), 21);
+        assert_eq!(infer_java_major("21w18a", ""), 8);
+        assert_eq!(infer_java_major("21w19a", ""), 16);
+        assert_eq!(infer_java_major("22w11a", ""), 17);
+        assert_eq!(infer_java_major("24w14a", ""), 21);
+        assert_eq!(infer_java_major("b1.7.3", ""), 8);
+        assert_eq!(infer_java_major("rd-132211", ""), 8);
+    }
+
+    #[test]
+    fn declared_java_version_wins() {
+        let client: Client = serde_json::from_str(
+            r#"{"id":"1.16.5","libraries":[],"mainClass":"M","releaseTime":"2021-01-14",
+                "time":"","type":"release","javaVersion":{"component":"jre-legacy","majorVersion":8}}"#,
+        )
+        .unwrap();
+        assert_eq!(client.java(), 8);
+        assert!(client.check_java(8).is_ok());
+        assert!(client.check_java(17).is_ok());
+        assert!(matches!(
+            client.check_java(7),
+            Err(JavaError::VersionMismatch(8, 7))
+        ));
     }
 }
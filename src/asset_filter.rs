@@ -0,0 +1,231 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    api::assets::{Assets, AssetsObject},
+    util::glob_match,
+};
+
+/// Language always installed, the game falls back to it
+pub const DEFAULT_LANGUAGE: &str = "en_us";
+
+/// Keys the game reads on startup, kept whatever the filter says
+pub const ESSENTIAL_ASSETS: &[&str] = &[
+    "pack.mcmeta",
+    "pack.png",
+    "icons/**",
+    "minecraft/pack.mcmeta",
+    "minecraft/sounds.json",
+    "minecraft/icons/**",
+];
+
+/// Sounds and music of the modern and pre-1.7 layouts
+const SOUND_ASSETS: &[&str] = &[
+    "minecraft/sounds/**",
+    "sound/**",
+    "sounds/**",
+    "newsound/**",
+    "music/**",
+    "newmusic/**",
+    "records/**",
+    "streaming/**",
+];
+
+/// Which assets of an index `ResourceUtil::fetch` installs
+#[derive(Debug, Clone, Default)]
+pub struct AssetFilter {
+    /// Language codes like `de_de` to install, every language when `None`
+    pub languages: Option<Vec<String>>,
+    /// Globs of the keys to install, every key when empty
+    pub include: Vec<String>,
+    /// Globs of the keys to skip, applied after `include`
+    pub exclude: Vec<String>,
+}
+impl AssetFilter {
+    pub fn new() -> Self {
+        AssetFilter::default()
+    }
+    /// Only the default language and no sounds, for headless clients and bots
+    pub fn minimal() -> Self {
+        let mut filter = AssetFilter::new().with_languages(&[DEFAULT_LANGUAGE]);
+        for pattern in SOUND_ASSETS {
+            filter = filter.with_exclude(pattern);
+        }
+        filter
+    }
+    /// Languages to install besides the default one
+    pub fn with_languages(mut self, languages: &[&str]) -> Self {
+        self.languages = Some(
+            languages
+                .iter()
+                .map(|language| language.to_lowercase())
+                .collect(),
+        );
+        self
+    }
+    pub fn with_include(mut self, pattern: &str) -> Self {
+        self.include.push(pattern.to_owned());
+        self
+    }
+    pub fn with_exclude(mut self, pattern: &str) -> Self {
+        self.exclude.push(pattern.to_owned());
+        self
+    }
+    /// Whether the asset with the key is installed
+    pub fn allows(&self, key: &str) -> bool {
+        if ESSENTIAL_ASSETS
+            .iter()
+            .any(|pattern| glob_match(pattern, key))
+        {
+            return true;
+        }
+        let excluded = self.exclude.iter().any(|pattern| glob_match(pattern, key));
+        // language files are chosen by the allowlist, not by `include`
+        if let Some(language) = language_of(key) {
+            return language == DEFAULT_LANGUAGE
+                || (!excluded
+                    && self
+                        .languages
+                        .as_ref()
+                        .is_none_or(|languages| languages.contains(&language)));
+        }
+        let included =
+            self.include.is_empty() || self.include.iter().any(|pattern| glob_match(pattern, key));
+        included && !excluded
+    }
+    /// Index with only the allowed keys, the other keys are reported as skipped
+    pub fn apply(&self, assets: &Assets) -> (Assets, FilterReport) {
+        let (kept, skipped): (HashMap<_, _>, HashMap<_, _>) = assets
+            .objects
+            .iter()
+            .map(|(key, object)| (key.clone(), object.clone()))
+            .partition(|(key, _)| self.allows(key));
+        let report = FilterReport::new(&kept, &skipped);
+        let filtered = Assets {
+            objects: kept,
+            ..assets.clone()
+        };
+        (filtered, report)
+    }
+}
+
+/// Language code of a language file key, lowercase as in `minecraft/lang/en_us.json`
+fn language_of(key: &str) -> Option<String> {
+    let (directory, file) = key.rsplit_once('/')?;
+    if !(directory == "lang" || directory.ends_with("/lang")) {
+        return None;
+    }
+    let (language, _) = file.rsplit_once('.')?;
+    Some(language.to_lowercase())
+}
+
+/// What an `AssetFilter` saved, objects shared by kept keys count as kept
+#[derive(Debug, Clone, Default)]
+pub struct FilterReport {
+    pub kept_keys: usize,
+    pub skipped_keys: usize,
+    pub kept_bytes: u64,
+    pub skipped_bytes: u64,
+}
+impl FilterReport {
+    fn new(kept: &HashMap<String, AssetsObject>, skipped: &HashMap<String, AssetsObject>) -> Self {
+        // the same object can be stored under several keys
+        let mut kept_hashes = HashSet::new();
+        let kept_bytes = kept
+            .values()
+            .filter(|object| kept_hashes.insert(&object.hash))
+            .map(|object| object.size)
+            .sum();
+        let mut skipped_hashes = HashSet::new();
+        let skipped_bytes = skipped
+            .values()
+            .filter(|object| !kept_hashes.contains(&object.hash))
+            .filter(|object| skipped_hashes.insert(&object.hash))
+            .map(|object| object.size)
+            .sum();
+        FilterReport {
+            kept_keys: kept.len(),
+            skipped_keys: skipped.len(),
+            kept_bytes,
+            skipped_bytes,
+        }
+    }
+    /// Share of the download size saved, from 0 to 100
+    pub fn saved_percent(&self) -> f64 {
+        let total = self.kept_bytes + self.skipped_bytes;
+        if total == 0 {
+            return 0.0;
+        }
+        self.skipped_bytes as f64 * 100.0 / total as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assets() -> Assets {
+        serde_json::from_str(
+            r#"{"objects":{
+                "minecraft/lang/en_us.json":{"hash":"aa","size":10},
+                "minecraft/lang/de_de.json":{"hash":"bb","size":20},
+                "minecraft/lang/fr_fr.json":{"hash":"cc","size":30},
+                "minecraft/sounds/mob/cow/say1.ogg":{"hash":"dd","size":40},
+                "minecraft/sounds/mob/cow/say2.ogg":{"hash":"dd","size":40},
+                "minecraft/sounds.json":{"hash":"ee","size":5},
+                "icons/icon_16x16.png":{"hash":"ff","size":5},
+                "minecraft/textures/a.png":{"hash":"aa","size":10}
+            }}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn languages_are_chosen_by_the_allowlist() {
+        let filter = AssetFilter::new().with_languages(&["DE_DE"]);
+        assert!(filter.allows("minecraft/lang/en_us.json"));
+        assert!(filter.allows("minecraft/lang/de_de.json"));
+        assert!(!filter.allows("minecraft/lang/fr_fr.json"));
+        // pre-1.13 names and legacy directories
+        assert!(filter.allows("lang/de_DE.lang"));
+        assert!(!filter.allows("lang/fr_FR.lang"));
+        // include doesn't restrict the languages
+        let filter = AssetFilter::new().with_include("minecraft/textures/**");
+        assert!(filter.allows("minecraft/lang/fr_fr.json"));
+        assert!(!filter.allows("minecraft/sounds/mob/cow/say1.ogg"));
+    }
+
+    #[test]
+    fn exclude_wins_except_for_essentials() {
+        let filter = AssetFilter::new()
+            .with_exclude("minecraft/lang/**")
+            .with_exclude("icons/**")
+            .with_exclude("minecraft/sounds/**");
+        assert!(filter.allows("minecraft/lang/en_us.json"));
+        assert!(!filter.allows("minecraft/lang/de_de.json"));
+        assert!(filter.allows("icons/icon_16x16.png"));
+        assert!(!filter.allows("minecraft/sounds/mob/cow/say1.ogg"));
+        assert!(filter.allows("minecraft/textures/a.png"));
+    }
+
+    #[test]
+    fn minimal_profile() {
+        let filter = AssetFilter::minimal();
+        assert!(filter.allows("minecraft/sounds.json"));
+        assert!(!filter.allows("minecraft/sounds/mob/cow/say1.ogg"));
+        assert!(!filter.allows("newsound/step/grass1.ogg"));
+        assert!(!filter.allows("minecraft/lang/de_de.json"));
+        assert!(filter.allows("minecraft/textures/a.png"));
+    }
+
+    #[test]
+    fn report_counts_shared_objects_once() {
+        let (filtered, report) = AssetFilter::minimal().apply(&assets());
+        assert_eq!(filtered.objects.len(), 4);
+        assert_eq!(report.kept_keys, 4);
+        assert_eq!(report.skipped_keys, 4);
+        // en_us and the texture share an object, both cows share another one
+        assert_eq!(report.kept_bytes, 20);
+        assert_eq!(report.skipped_bytes, 90);
+        assert!((report.saved_percent() - 90.0 * 100.0 / 110.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,441 @@
+use std::{thread, time::Duration};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::debug;
+
+use crate::{
+    auth::{Session, now},
+    errors::{AuthError, HttpError},
+    http,
+};
+
+pub const DEVICE_CODE_URL: &str =
+    "https://login.microsoftonline.com/consumers/oauth2/v2.0/devicecode";
+pub const TOKEN_URL: &str = "https://login.microsoftonline.com/consumers/oauth2/v2.0/token";
+pub const XBOX_USER_URL: &str = "https://user.auth.xboxlive.com/user/authenticate";
+pub const XSTS_URL: &str = "https://xsts.auth.xboxlive.com/xsts/authorize";
+pub const MINECRAFT_LOGIN_URL: &str =
+    "https://api.minecraftservices.com/authentication/login_with_xbox";
+pub const PROFILE_URL: &str = "https://api.minecraftservices.com/minecraft/profile";
+pub const SCOPE: &str = "XboxLive.signin offline_access";
+
+#[derive(Debug, Clone)]
+pub struct MicrosoftEndpoints {
+    pub device_code: String,
+    pub token: String,
+    pub xbox_user: String,
+    pub xsts: String,
+    pub minecraft_login: String,
+    pub profile: String,
+}
+impl Default for MicrosoftEndpoints {
+    fn default() -> Self {
+        MicrosoftEndpoints {
+            device_code: DEVICE_CODE_URL.to_owned(),
+            token: TOKEN_URL.to_owned(),
+            xbox_user: XBOX_USER_URL.to_owned(),
+            xsts: XSTS_URL.to_owned(),
+            minecraft_login: MINECRAFT_LOGIN_URL.to_owned(),
+            profile: PROFILE_URL.to_owned(),
+        }
+    }
+}
+impl MicrosoftEndpoints {
+    /// Every endpoint under a single base url, keeping the original paths
+    pub fn with_base(base: &str) -> Self {
+        let base = base.trim_end_matches('/');
+        MicrosoftEndpoints {
+            device_code: format!("{}/consumers/oauth2/v2.0/devicecode", base),
+            token: format!("{}/consumers/oauth2/v2.0/token", base),
+            xbox_user: format!("{}/user/authenticate", base),
+            xsts: format!("{}/xsts/authorize", base),
+            minecraft_login: format!("{}/authentication/login_with_xbox", base),
+            profile: format!("{}/minecraft/profile", base),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct DeviceCode {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub expires_in: u64,
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+    #[serde(default)]
+    pub message: String,
+}
+fn default_interval() -> u64 {
+    5
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct MicrosoftToken {
+    pub access_token: String,
+    #[serde(default)]
+    pub refresh_token: String,
+    pub expires_in: u64,
+}
+
+/// Result of a full login, the refresh token allows logging in again without the user
+#[derive(Debug, Clone)]
+pub struct MicrosoftAccount {
+    pub session: Session,
+    pub refresh_token: String,
+}
+
+#[derive(Deserialize)]
+struct OAuthError {
+    error: String,
+}
+#[derive(Deserialize)]
+struct XboxResponse {
+    #[serde(alias = "Token")]
+    token: String,
+    #[serde(alias = "DisplayClaims")]
+    display_claims: XboxClaims,
+}
+#[derive(Deserialize)]
+struct XboxClaims {
+    xui: Vec<XboxUser>,
+}
+#[derive(Deserialize)]
+struct XboxUser {
+    uhs: String,
+    #[serde(default)]
+    xid: Option<String>,
+}
+#[derive(Deserialize)]
+struct XstsError {
+    #[serde(alias = "XErr")]
+    xerr: u64,
+}
+#[derive(Deserialize)]
+struct MinecraftToken {
+    access_token: String,
+    expires_in: u64,
+}
+#[derive(Deserialize)]
+struct MinecraftProfile {
+    id: String,
+    name: String,
+}
+
+/// Microsoft device code flow followed by the Xbox Live, XSTS and Minecraft exchange
+pub struct MicrosoftAuth {
+    pub client_id: String,
+    pub scope: String,
+    pub endpoints: MicrosoftEndpoints,
+}
+impl MicrosoftAuth {
+    /// `client_id` is the id of the Azure application registered for the launcher
+    pub fn new(client_id: &str) -> Self {
+        MicrosoftAuth {
+            client_id: client_id.to_owned(),
+            scope: SCOPE.to_owned(),
+            endpoints: MicrosoftEndpoints::default(),
+        }
+    }
+    pub fn with_endpoints(mut self, endpoints: MicrosoftEndpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+    pub fn with_scope(mut self, scope: &str) -> Self {
+        self.scope = scope.to_owned();
+        self
+    }
+    /// Runs the whole flow, `on_code` must show the user code and url to the user
+    pub fn authenticate(
+        &self,
+        on_code: impl FnOnce(&DeviceCode),
+    ) -> Result<MicrosoftAccount, AuthError> {
+        let code = self.device_code()?;
+        on_code(&code);
+        let token = self.poll(&code)?;
+        self.login(&token)
+    }
+    pub fn device_code(&self) -> Result<DeviceCode, AuthError> {
+        let response = http::post_form(
+            &self.endpoints.device_code,
+            &[("client_id", &self.client_id), ("scope", &self.scope)],
+        )?;
+        Ok(response.success_json()?)
+    }
+    /// Waits until the user completes the login of the device code
+    pub fn poll(&self, code: &DeviceCode) -> Result<MicrosoftToken, AuthError> {
+        let deadline = now() + code.expires_in;
+        let mut interval = code.interval;
+        loop {
+            if now() >= deadline {
+                return Err(AuthError::Expired());
+            }
+            let response = http::post_form(
+                &self.endpoints.token,
+                &[
+                    ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+                    ("client_id", &self.client_id),
+                    ("device_code", &code.device_code),
+                ],
+            )?;
+            if response.is_success() {
+                return Ok(response.json()?);
+            }
+            let error: OAuthError = response
+                .json()
+                .map_err(|_| HttpError::Status(response.status, response.body.clone()))?;
+            debug!("POLL {}", error.error);
+            match error.error.as_str() {
+                "authorization_pending" => {}
+                "slow_down" => interval += 5,
+                "authorization_declined" | "access_denied" => return Err(AuthError::Declined()),
+                "expired_token" | "code_expired" => return Err(AuthError::Expired()),
+                _ => return Err(HttpError::Status(response.status, response.body).into()),
+            }
+            thread::sleep(Duration::from_secs(interval));
+        }
+    }
+    pub fn refresh(&self, refresh_token: &str) -> Result<MicrosoftToken, AuthError> {
+        let response = http::post_form(
+            &self.endpoints.token,
+            &[
+                ("grant_type", "refresh_token"),
+                ("client_id", &self.client_id),
+                ("scope", &self.scope),
+                ("refresh_token", refresh_token),
+            ],
+        )?;
+        Ok(response.success_json()?)
+    }
+    /// Exchanges a Microsoft token for the Minecraft session
+    pub fn login(&self, token: &MicrosoftToken) -> Result<MicrosoftAccount, AuthError> {
+        let xbox: XboxResponse = http::post_json(
+            &self.endpoints.xbox_user,
+            &json!({
+                "Properties": {
+                    "AuthMethod": "RPS",
+                    "SiteName": "user.auth.xboxlive.com",
+                    "RpsTicket": format!("d={}", token.access_token),
+                },
+                "RelyingParty": "http://auth.xboxlive.com",
+                "TokenType": "JWT",
+            }),
+        )?
+        .success_json()?;
+
+        let response = http::post_json(
+            &self.endpoints.xsts,
+            &json!({
+                "Properties": {
+                    "SandboxId": "RETAIL",
+                    "UserTokens": [xbox.token],
+                },
+                "RelyingParty": "rp://api.minecraftservices.com/",
+                "TokenType": "JWT",
+            }),
+        )?;
+        if response.status == 401 {
+            let error: XstsError = response.json()?;
+            return Err(match error.xerr {
+                2148916233 => AuthError::NoXboxAccount(),
+                2148916235 => AuthError::XboxUnavailable(),
+                2148916236..=2148916238 => AuthError::ChildAccount(),
+                _ => HttpError::Status(response.status, response.body).into(),
+            });
+        }
+        let xsts: XboxResponse = response.success_json()?;
+        let user = xsts
+            .display_claims
+            .xui
+            .first()
+            .ok_or_else(|| AuthError::InvalidResponse(self.endpoints.xsts.clone()))?;
+
+        let minecraft: MinecraftToken = http::post_json(
+            &self.endpoints.minecraft_login,
+            &json!({ "identityToken": format!("XBL3.0 x={};{}", user.uhs, xsts.token) }),
+        )?
+        .success_json()?;
+
+        let response = http::get(&self.endpoints.profile, Some(&minecraft.access_token))?;
+        if response.status == 404 {
+            return Err(AuthError::NotOwned());
+        }
+        let profile: MinecraftProfile = response.success_json()?;
+
+        let xuid = user
+            .xid
+            .clone()
+            .or_else(|| token_claim(&minecraft.access_token, "xuid"))
+            .unwrap_or_default();
+        debug!("LOGGED AS {} {}", profile.name, profile.id);
+        Ok(MicrosoftAccount {
+            session: Session {
+                name: profile.name,
+                uuid: profile.id,
+                access_token: minecraft.access_token,
+                xuid,
+                client_id: self.client_id.clone(),
+                user_type: String::from("msa"),
+                expires_at: now() + minecraft.expires_in,
+            },
+            refresh_token: token.refresh_token.clone(),
+        })
+    }
+    /// Logs in again from a stored refresh token
+    pub fn login_refresh(&self, refresh_token: &str) -> Result<MicrosoftAccount, AuthError> {
+        let mut token = self.refresh(refresh_token)?;
+        if token.refresh_token.is_empty() {
+            token.refresh_token = refresh_token.to_owned();
+        }
+        self.login(&token)
+    }
+}
+
+/// Reads a claim of the JWT payload without verifying it
+fn token_claim(token: &str, claim: &str) -> Option<String> {
+    let payload = token.split('.').nth(1)?;
+    let payload = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+    let payload: serde_json::Value = serde_json::from_slice(&payload).ok()?;
+    match payload.get(claim)? {
+        serde_json::Value::String(value) => Some(value.clone()),
+        value => Some(value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+    use crate::http::testing::serve;
+
+    /// Xbox and Minecraft services answering with the statuses of XSTS and the profile
+    fn services(xsts: u16, profile: u16) -> MicrosoftAuth {
+        let base = serve(move |request| match request.path.as_str() {
+            "/consumers/oauth2/v2.0/token" => {
+                assert!(request.body.contains("grant_type=refresh_token"));
+                assert!(request.body.contains("refresh_token=old"));
+                (
+                    200,
+                    json!({ "access_token": "ms", "expires_in": 3600 }).to_string(),
+                )
+            }
+            "/user/authenticate" => {
+                assert!(request.body.contains("d=ms"));
+                let body =
+                    json!({ "Token": "xbl", "DisplayClaims": { "xui": [{ "uhs": "hash" }] } });
+                (200, body.to_string())
+            }
+            "/xsts/authorize" if xsts == 401 => (401, json!({ "XErr": 2148916233u64 }).to_string()),
+            "/xsts/authorize" => {
+                assert!(request.body.contains("\"xbl\""));
+                let body = json!({
+                    "Token": "xsts",
+                    "DisplayClaims": { "xui": [{ "uhs": "hash", "xid": "2535" }] },
+                });
+                (200, body.to_string())
+            }
+            "/authentication/login_with_xbox" => {
+                assert!(request.body.contains("XBL3.0 x=hash;xsts"));
+                (
+                    200,
+                    json!({ "access_token": "mc", "expires_in": 86400 }).to_string(),
+                )
+            }
+            "/minecraft/profile" if profile == 404 => (404, String::new()),
+            "/minecraft/profile" => {
+                assert_eq!(request.header("Authorization"), Some("Bearer mc"));
+                let body = json!({ "id": "069a79f444e94726a5befca90e38aaf5", "name": "Notch" });
+                (200, body.to_string())
+            }
+            path => panic!("unexpected request {} {}", request.method, path),
+        });
+        MicrosoftAuth::new("client").with_endpoints(MicrosoftEndpoints::with_base(&base))
+    }
+
+    #[test]
+    fn login_refresh_returns_the_session() {
+        let account = services(200, 200).login_refresh("old").unwrap();
+        assert_eq!(account.session.name, "Notch");
+        assert_eq!(account.session.uuid, "069a79f444e94726a5befca90e38aaf5");
+        assert_eq!(account.session.access_token, "mc");
+        assert_eq!(account.session.xuid, "2535");
+        assert_eq!(account.session.user_type, "msa");
+        // the token endpoint didn't rotate it
+        assert_eq!(account.refresh_token, "old");
+    }
+
+    #[test]
+    fn login_without_xbox_account() {
+        let result = services(401, 200).login_refresh("old");
+        assert!(matches!(result, Err(AuthError::NoXboxAccount())));
+    }
+
+    #[test]
+    fn login_without_the_game() {
+        let result = services(200, 404).login_refresh("old");
+        assert!(matches!(result, Err(AuthError::NotOwned())));
+    }
+
+    #[test]
+    fn poll_waits_for_the_user() {
+        let polls = Arc::new(AtomicUsize::new(0));
+        let counter = polls.clone();
+        let base = serve(move |request| match request.path.as_str() {
+            "/consumers/oauth2/v2.0/devicecode" => {
+                assert!(request.body.contains("client_id=client"));
+                let body = json!({
+                    "device_code": "device",
+                    "user_code": "ABCD",
+                    "verification_uri": "https://microsoft.com/link",
+                    "expires_in": 60,
+                    "interval": 0,
+                });
+                (200, body.to_string())
+            }
+            "/consumers/oauth2/v2.0/token" => match counter.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => (400, json!({ "error": "authorization_pending" }).to_string()),
+                _ => {
+                    assert!(request.body.contains("device_code=device"));
+                    let body =
+                        json!({ "access_token": "ms", "refresh_token": "new", "expires_in": 3600 });
+                    (200, body.to_string())
+                }
+            },
+            path => panic!("unexpected request {} {}", request.method, path),
+        });
+        let auth =
+            MicrosoftAuth::new("client").with_endpoints(MicrosoftEndpoints::with_base(&base));
+        let code = auth.device_code().unwrap();
+        assert_eq!(code.user_code, "ABCD");
+        let token = auth.poll(&code).unwrap();
+        assert_eq!(token.refresh_token, "new");
+        assert_eq!(polls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn poll_declined() {
+        let base = serve(|_| {
+            (
+                400,
+                json!({ "error": "authorization_declined" }).to_string(),
+            )
+        });
+        let auth =
+            MicrosoftAuth::new("client").with_endpoints(MicrosoftEndpoints::with_base(&base));
+        let code = DeviceCode {
+            device_code: String::from("device"),
+            user_code: String::from("ABCD"),
+            verification_uri: String::new(),
+            expires_in: 60,
+            interval: 0,
+            message: String::new(),
+        };
+        assert!(matches!(auth.poll(&code), Err(AuthError::Declined())));
+    }
+}
@@ -0,0 +1,52 @@
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub mod microsoft;
+pub mod offline;
+pub mod store;
+pub mod yggdrasil;
+
+/// Values of an authenticated player, used to fill the launch placeholders
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    pub name: String,
+    pub uuid: String,
+    pub access_token: String,
+    pub xuid: String,
+    pub client_id: String,
+    pub user_type: String,
+    /// Unix time in seconds when the access token expires, 0 if it doesn't
+    pub expires_at: u64,
+}
+impl Session {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at != 0 && self.expires_at <= now()
+    }
+    pub fn apply(&self, data: &mut HashMap<String, String>) {
+        data.insert("auth_player_name".to_owned(), self.name.clone());
+        data.insert("auth_uuid".to_owned(), self.uuid.clone());
+        data.insert("auth_access_token".to_owned(), self.access_token.clone());
+        data.insert("auth_xuid".to_owned(), self.xuid.clone());
+        data.insert("clientid".to_owned(), self.client_id.clone());
+        data.insert("user_type".to_owned(), self.user_type.clone());
+        // legacy versions take the token and uuid in a single argument
+        data.insert(
+            "auth_session".to_owned(),
+            format!("token:{}:{}", self.access_token, self.uuid),
+        );
+    }
+    pub fn data(&self) -> HashMap<String, String> {
+        let mut data = HashMap::new();
+        self.apply(&mut data);
+        data
+    }
+}
+
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,73 @@
+use md5::{Digest, Md5};
+
+use crate::{auth::Session, errors::AuthError};
+
+/// Account without authentication, for LAN and servers in offline mode
+#[derive(Debug, Clone)]
+pub struct OfflineAccount {
+    pub name: String,
+    pub uuid: String,
+}
+impl OfflineAccount {
+    pub fn new(name: &str) -> Result<Self, AuthError> {
+        if !Self::is_valid_name(name) {
+            return Err(AuthError::InvalidUsername(name.to_owned()));
+        }
+        Ok(OfflineAccount {
+            name: name.to_owned(),
+            uuid: offline_uuid(name),
+        })
+    }
+    /// Names accepted by the game, 3 to 16 letters, digits or underscores
+    pub fn is_valid_name(name: &str) -> bool {
+        (3..=16).contains(&name.len())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+    /// UUID in the `8-4-4-4-12` form, as shown by the game
+    pub fn hyphenated(&self) -> String {
+        format!(
+            "{}-{}-{}-{}-{}",
+            &self.uuid[..8],
+            &self.uuid[8..12],
+            &self.uuid[12..16],
+            &self.uuid[16..20],
+            &self.uuid[20..]
+        )
+    }
+    pub fn session(&self) -> Session {
+        Session {
+            name: self.name.clone(),
+            uuid: self.uuid.clone(),
+            access_token: String::from("0"),
+            user_type: String::from("legacy"),
+            ..Default::default()
+        }
+    }
+}
+
+/// Name based UUID (v3) of `OfflinePlayer:<name>`, the one the server derives in offline mode
+pub fn offline_uuid(name: &str) -> String {
+    let mut hash: [u8; 16] = Md5::digest(format!("OfflinePlayer:{}", name)).into();
+    hash[6] = (hash[6] & 0x0f) | 0x30;
+    hash[8] = (hash[8] & 0x3f) | 0x80;
+    hash.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offline_uuid_matches_the_server() {
+        let account = OfflineAccount::new("Notch").unwrap();
+        assert_eq!(account.uuid, "b50ad385829d3141a2167e7d7539ba7f");
+        assert_eq!(account.hyphenated(), "b50ad385-829d-3141-a216-7e7d7539ba7f");
+    }
+
+    #[test]
+    fn invalid_names() {
+        for name in ["ab", "seventeen_letters", "no space", "ümlaut"] {
+            assert!(OfflineAccount::new(name).is_err(), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::{
+    auth::{
+        Session,
+        microsoft::{MicrosoftAccount, MicrosoftAuth},
+        now,
+        offline::OfflineAccount,
+        yggdrasil::{YggdrasilAccount, YggdrasilAuth},
+    },
+    errors::AuthError,
+};
+
+/// Tokens are refreshed this many seconds before they expire
+pub const REFRESH_MARGIN: u64 = 300;
+
+/// Backend holding the serialized accounts, implement it to keep them in a keyring
+pub trait AccountStorage {
+    fn load(&self) -> Result<Option<String>, AuthError>;
+    fn save(&self, content: &str) -> Result<(), AuthError>;
+}
+
+/// Stores the accounts in a json file readable only by the owner
+pub struct FileStorage {
+    pub path: PathBuf,
+}
+impl FileStorage {
+    pub fn new(path: &str) -> Self {
+        FileStorage {
+            path: PathBuf::from(path),
+        }
+    }
+}
+impl AccountStorage for FileStorage {
+    fn load(&self) -> Result<Option<String>, AuthError> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(fs::read_to_string(&self.path)?))
+    }
+    fn save(&self, content: &str) -> Result<(), AuthError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = self.path.with_extension("tmp");
+        // a leftover file would keep its permissions
+        if tmp.exists() {
+            fs::remove_file(&tmp)?;
+        }
+        create_private(&tmp)?.write_all(content.as_bytes())?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+/// New file readable only by the owner from the moment it's created
+fn create_private(path: &Path) -> Result<File, std::io::Error> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AccountKind {
+    Microsoft,
+    Offline,
+    Yggdrasil,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StoredAccount {
+    pub kind: AccountKind,
+    pub name: String,
+    pub uuid: String,
+    #[serde(default)]
+    pub access_token: String,
+    #[serde(default)]
+    pub refresh_token: String,
+    #[serde(default)]
+    pub xuid: String,
+    #[serde(default)]
+    pub client_id: String,
+    #[serde(default)]
+    pub expires_at: u64,
+    /// Api root of the auth server, only for Yggdrasil accounts
+    #[serde(default)]
+    pub server: String,
+}
+impl StoredAccount {
+    pub fn needs_refresh(&self) -> bool {
+        self.expires_at != 0 && self.expires_at <= now() + REFRESH_MARGIN
+    }
+    pub fn session(&self) -> Session {
+        Session {
+            name: self.name.clone(),
+            uuid: self.uuid.clone(),
+            access_token: self.access_token.clone(),
+            xuid: self.xuid.clone(),
+            client_id: self.client_id.clone(),
+            user_type: match self.kind {
+                AccountKind::Microsoft => String::from("msa"),
+                AccountKind::Offline => String::from("legacy"),
+                AccountKind::Yggdrasil => String::from("mojang"),
+            },
+            expires_at: self.expires_at,
+        }
+    }
+}
+impl From<MicrosoftAccount> for StoredAccount {
+    fn from(account: MicrosoftAccount) -> Self {
+        let session = account.session;
+        StoredAccount {
+            kind: AccountKind::Microsoft,
+            name: session.name,
+            uuid: session.uuid,
+            access_token: session.access_token,
+            refresh_token: account.refresh_token,
+            xuid: session.xuid,
+            client_id: session.client_id,
+            expires_at: session.expires_at,
+            server: String::new(),
+        }
+    }
+}
+impl From<YggdrasilAccount> for StoredAccount {
+    fn from(account: YggdrasilAccount) -> Self {
+        let session = account.session;
+        StoredAccount {
+            kind: AccountKind::Yggdrasil,
+            name: session.name,
+            uuid: session.uuid,
+            access_token: session.access_token,
+            refresh_token: String::new(),
+            xuid: String::new(),
+            client_id: session.client_id,
+            expires_at: 0,
+            server: account.server,
+        }
+    }
+}
+impl From<OfflineAccount> for StoredAccount {
+    fn from(account: OfflineAccount) -> Self {
+        let session = account.session();
+        StoredAccount {
+            kind: AccountKind::Offline,
+            name: session.name,
+            uuid: session.uuid,
+            access_token: session.access_token,
+            refresh_token: String::new(),
+            xuid: String::new(),
+            client_id: String::new(),
+            expires_at: 0,
+            server: String::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct AccountsFile {
+    selected: Option<String>,
+    accounts: Vec<StoredAccount>,
+}
+
+/// Accounts saved between launches, one of them selected as default
+pub struct AccountStore<S: AccountStorage> {
+    storage: S,
+    data: AccountsFile,
+    microsoft: Option<MicrosoftAuth>,
+}
+impl AccountStore<FileStorage> {
+    pub fn open(path: &str) -> Result<Self, AuthError> {
+        Self::new(FileStorage::new(path))
+    }
+}
+impl<S: AccountStorage> AccountStore<S> {
+    pub fn new(storage: S) -> Result<Self, AuthError> {
+        let data = match storage.load()? {
+            Some(content) => serde_json::from_str(&content)?,
+            None => AccountsFile::default(),
+        };
+        Ok(AccountStore {
+            storage,
+            data,
+            microsoft: None,
+        })
+    }
+    /// Flow used to refresh Microsoft accounts
+    pub fn with_microsoft(mut self, auth: MicrosoftAuth) -> Self {
+        self.microsoft = Some(auth);
+        self
+    }
+    pub fn accounts(&self) -> &[StoredAccount] {
+        &self.data.accounts
+    }
+    pub fn get(&self, uuid: &str) -> Option<&StoredAccount> {
+        self.data.accounts.iter().find(|a| a.uuid == uuid)
+    }
+    pub fn selected(&self) -> Option<&StoredAccount> {
+        self.get(self.data.selected.as_ref()?)
+    }
+    /// Adds or replaces the account with the same uuid, the first one becomes the default
+    pub fn add(&mut self, account: impl Into<StoredAccount>) -> Result<(), AuthError> {
+        let account = account.into();
+        if self.data.selected.is_none() {
+            self.data.selected = Some(account.uuid.clone());
+        }
+        match self
+            .data
+            .accounts
+            .iter_mut()
+            .find(|a| a.uuid == account.uuid)
+        {
+            Some(stored) => *stored = account,
+            None => self.data.accounts.push(account),
+        }
+        self.save()
+    }
+    pub fn remove(&mut self, uuid: &str) -> Result<(), AuthError> {
+        self.data.accounts.retain(|a| a.uuid != uuid);
+        if self.data.selected.as_deref() == Some(uuid) {
+            self.data.selected = self.data.accounts.first().map(|a| a.uuid.clone());
+        }
+        self.save()
+    }
+    pub fn select(&mut self, uuid: &str) -> Result<(), AuthError> {
+        if self.get(uuid).is_none() {
+            return Err(AuthError::AccountNotFound(uuid.to_owned()));
+        }
+        self.data.selected = Some(uuid.to_owned());
+        self.save()
+    }
+    /// Session of the account, refreshing and saving its tokens when they expired
+    pub fn session(&mut self, uuid: &str) -> Result<Session, AuthError> {
+        let account = self
+            .get(uuid)
+            .ok_or_else(|| AuthError::AccountNotFound(uuid.to_owned()))?;
+        if !self.needs_refresh(account)? {
+            return Ok(account.session());
+        }
+        debug!("REFRESH ACCOUNT {}", account.name);
+        let refreshed = self.refresh(account)?;
+        let session = refreshed.session();
+        self.add(refreshed)?;
+        Ok(session)
+    }
+    pub fn selected_session(&mut self) -> Result<Session, AuthError> {
+        let uuid = self
+            .data
+            .selected
+            .clone()
+            .ok_or_else(|| AuthError::AccountNotFound(String::from("selected")))?;
+        self.session(&uuid)
+    }
+    /// Fills the auth placeholders with the selected account
+    pub fn apply(&mut self, data: &mut HashMap<String, String>) -> Result<(), AuthError> {
+        self.selected_session()?.apply(data);
+        Ok(())
+    }
+    fn refresh(&self, account: &StoredAccount) -> Result<StoredAccount, AuthError> {
+        match account.kind {
+            AccountKind::Microsoft => {
+                let auth = self
+                    .microsoft
+                    .as_ref()
+                    .ok_or_else(|| AuthError::NoProvider(String::from("microsoft")))?;
+                Ok(auth.login_refresh(&account.refresh_token)?.into())
+            }
+            AccountKind::Yggdrasil => Ok(Self::yggdrasil(account)
+                .refresh(&account.access_token)?
+                .into()),
+            AccountKind::Offline => Ok(account.clone()),
+        }
+    }
+    /// Yggdrasil tokens carry no expiry, the server is asked instead
+    fn needs_refresh(&self, account: &StoredAccount) -> Result<bool, AuthError> {
+        match account.kind {
+            AccountKind::Yggdrasil => {
+                Ok(!Self::yggdrasil(account).validate(&account.access_token)?)
+            }
+            _ => Ok(account.needs_refresh()),
+        }
+    }
+    fn yggdrasil(account: &StoredAccount) -> YggdrasilAuth {
+        YggdrasilAuth::new(&account.server).with_client_token(&account.client_id)
+    }
+    fn save(&self) -> Result<(), AuthError> {
+        self.storage
+            .save(&serde_json::to_string_pretty(&self.data)?)
+    }
+}
@@ -0,0 +1,199 @@
+use base64::{Engine, engine::general_purpose::STANDARD};
+use dwldutil::{DLFile, DLHashes};
+use md5::{Digest, Md5};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::debug;
+
+use crate::{
+    auth::{Session, now},
+    command::Command,
+    errors::{AuthError, HttpError},
+    http,
+};
+
+pub const AUTHLIB_INJECTOR_URL: &str = "https://authlib-injector.yushi.moe/artifact/latest.json";
+
+#[derive(Deserialize)]
+struct YggdrasilProfile {
+    id: String,
+    name: String,
+}
+#[derive(Deserialize)]
+struct YggdrasilResponse {
+    #[serde(alias = "accessToken")]
+    access_token: String,
+    #[serde(alias = "clientToken")]
+    client_token: String,
+    #[serde(alias = "selectedProfile")]
+    selected_profile: Option<YggdrasilProfile>,
+}
+#[derive(Deserialize)]
+struct YggdrasilError {
+    #[serde(default, alias = "errorMessage")]
+    error_message: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct YggdrasilAccount {
+    pub session: Session,
+    /// Api root of the server that issued the token
+    pub server: String,
+}
+
+/// Client for the legacy Yggdrasil authserver api, as served by Ely.by or self hosted servers
+pub struct YggdrasilAuth {
+    /// Api root, the authserver endpoints are under `<base_url>/authserver`
+    pub base_url: String,
+    pub client_token: String,
+}
+impl YggdrasilAuth {
+    pub fn new(base_url: &str) -> Self {
+        let seed = format!("{}:{}:{}", base_url, std::process::id(), now());
+        let client_token = Md5::digest(seed)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        YggdrasilAuth {
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            client_token,
+        }
+    }
+    /// Reuses the client token the access token was issued for
+    pub fn with_client_token(mut self, client_token: &str) -> Self {
+        self.client_token = client_token.to_owned();
+        self
+    }
+    fn endpoint(&self, name: &str) -> String {
+        format!("{}/authserver/{}", self.base_url, name)
+    }
+    pub fn authenticate(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<YggdrasilAccount, AuthError> {
+        let response = http::post_json(
+            &self.endpoint("authenticate"),
+            &json!({
+                "agent": { "name": "Minecraft", "version": 1 },
+                "username": username,
+                "password": password,
+                "clientToken": self.client_token,
+                "requestUser": true,
+            }),
+        )?;
+        self.account(response)
+    }
+    pub fn refresh(&self, access_token: &str) -> Result<YggdrasilAccount, AuthError> {
+        let response = http::post_json(
+            &self.endpoint("refresh"),
+            &json!({
+                "accessToken": access_token,
+                "clientToken": self.client_token,
+                "requestUser": true,
+            }),
+        )?;
+        self.account(response)
+    }
+    /// Whether the token is still accepted by the server
+    pub fn validate(&self, access_token: &str) -> Result<bool, AuthError> {
+        let response = http::post_json(
+            &self.endpoint("validate"),
+            &json!({
+                "accessToken": access_token,
+                "clientToken": self.client_token,
+            }),
+        )?;
+        match response.status {
+            200..=299 => Ok(true),
+            401 | 403 => Ok(false),
+            status => Err(HttpError::Status(status, response.body).into()),
+        }
+    }
+    fn account(&self, response: http::Response) -> Result<YggdrasilAccount, AuthError> {
+        if matches!(response.status, 401 | 403) {
+            let error: YggdrasilError = response.json()?;
+            return Err(AuthError::InvalidCredentials(error.error_message));
+        }
+        let response: YggdrasilResponse = response.success_json()?;
+        let profile = response.selected_profile.ok_or(AuthError::NotOwned())?;
+        debug!(
+            "LOGGED AS {} {} ON {}",
+            profile.name, profile.id, self.base_url
+        );
+        Ok(YggdrasilAccount {
+            session: Session {
+                name: profile.name,
+                uuid: profile.id,
+                access_token: response.access_token,
+                client_id: response.client_token,
+                user_type: String::from("mojang"),
+                ..Default::default()
+            },
+            server: self.base_url.clone(),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct InjectorArtifact {
+    download_url: String,
+    checksums: InjectorChecksums,
+}
+#[derive(Deserialize)]
+struct InjectorChecksums {
+    sha256: String,
+}
+
+/// Java agent that redirects the game authentication to a Yggdrasil server
+pub struct AuthlibInjector {
+    pub api_root: String,
+    pub artifact_url: String,
+    /// Base64 of the api metadata, passed so the agent doesn't request it at startup
+    pub metadata: Option<String>,
+}
+impl AuthlibInjector {
+    pub fn new(api_root: &str) -> Self {
+        AuthlibInjector {
+            api_root: api_root.to_owned(),
+            artifact_url: AUTHLIB_INJECTOR_URL.to_owned(),
+            metadata: None,
+        }
+    }
+    pub fn with_artifact_url(mut self, url: &str) -> Self {
+        self.artifact_url = url.to_owned();
+        self
+    }
+    /// Download of the latest authlib-injector jar
+    pub fn fetch(&self, path: &str) -> Result<DLFile, AuthError> {
+        let artifact: InjectorArtifact = http::get(&self.artifact_url, None)?.success_json()?;
+        Ok(DLFile::new()
+            .with_url(&artifact.download_url)
+            .with_path(path)
+            .with_hashes(DLHashes::new().sha256(&artifact.checksums.sha256)))
+    }
+    pub fn prefetch(mut self) -> Result<Self, AuthError> {
+        let response = http::get(&self.api_root, None)?;
+        if !response.is_success() {
+            return Err(HttpError::Status(response.status, response.body).into());
+        }
+        self.metadata = Some(STANDARD.encode(response.body));
+        Ok(self)
+    }
+    pub fn jvm_args(&self, jar: &str) -> Vec<String> {
+        let mut args = vec![format!("-javaagent:{}={}", jar, self.api_root)];
+        if let Some(metadata) = &self.metadata {
+            args.push(format!(
+                "-Dauthlibinjector.yggdrasil.prefetched={}",
+                metadata
+            ));
+        }
+        args
+    }
+    /// Adds the agent before the rest of the jvm arguments
+    pub fn apply(&self, jar: &str, command: &mut Command) {
+        let mut args = self.jvm_args(jar);
+        args.append(&mut command.jvm);
+        command.jvm = args;
+    }
+}
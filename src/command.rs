@@ -1,9 +1,16 @@
 use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
 
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use tracing::{debug, warn};
 
 use crate::api::client::{ArgumentValue, Client, ComplexArgument};
-use crate::util::{FillingUtil, resolve_rules_feat};
+use crate::errors::{CommandError, FillingError, JvmError};
+use crate::features::{Features, QUICK_PLAYS_SUPPORT};
+use crate::jvm::JvmPreset;
+use crate::process::{GameProcess, ProcessOptions};
+use crate::shell::Shell;
+use crate::util::{FillingMode, FillingUtil, resolve_rules_feat};
 
 pub struct Command {
     pub fill: FillingUtil,
@@ -11,46 +18,223 @@ pub struct Command {
     pub jvm: Vec<String>,
 }
 impl Command {
+    /// Arguments filled in strict mode, see `with_mode`
     pub fn from_args(game: Vec<String>, jvm: Vec<String>, data: HashMap<String, String>) -> Self {
         let fill = FillingUtil::new().with_data(data);
         Command { fill, game, jvm }
     }
+    /// Lenient mode passes placeholders without a value to java as written
+    pub fn with_mode(mut self, mode: FillingMode) -> Self {
+        self.fill.mode = mode;
+        self
+    }
+    /// Placeholders of the arguments that have no value
+    pub fn missing_keys(&self) -> Vec<String> {
+        let args: Vec<String> = self.jvm.iter().chain(self.game.iter()).cloned().collect();
+        self.fill.missing_keys(&args).unwrap_or_default()
+    }
 
-    fn build_args(&self, args: &Vec<String>) -> Vec<String> {
-        args.par_iter()
-            .map(|f| {
-                let mut filled = self.fill.fill(f.clone()).unwrap_or(f.clone());
-                if filled.len() >= 140 {
-                    filled.insert(0, '\"');
-                    filled.insert(filled.len(), '\"');
-                }
-                filled
-            })
-            .collect()
-    }
-    pub fn build_game_args(&self) -> Vec<String> {
-        Self::build_args(&self, &self.game)
-    }
-    pub fn build_jvm_args(&self) -> Vec<String> {
-        Self::build_args(&self, &self.jvm)
-    }
-    pub fn build(&self, extra: Vec<String>) -> Vec<String> {
-        let mut args = self.build_jvm_args();
-        let mut game = self.build_game_args();
-        let mut extra = extra.clone();
-        args.append(&mut extra);
-        args.append(&mut game);
+    pub fn build_game_args(&self) -> Result<Vec<String>, FillingError> {
+        self.fill.fill_all(&self.game)
+    }
+    pub fn build_jvm_args(&self) -> Result<Vec<String>, FillingError> {
+        self.fill.fill_all(&self.jvm)
+    }
+    /// Separates the filled arguments, the main class comes from the `main_class` value and
+    /// is required in both modes. Strict mode fails with the missing keys of every section
+    pub fn launch(&self) -> Result<LaunchCommand, FillingError> {
+        let main_class = self.fill.data.get("main_class");
+        if self.fill.mode == FillingMode::Strict {
+            let mut missing = self.missing_keys();
+            if main_class.is_none() {
+                missing.push(String::from("main_class"));
+            }
+            if !missing.is_empty() {
+                return Err(FillingError::MissingKeys(missing));
+            }
+        }
+        let main_class = main_class
+            .ok_or_else(|| FillingError::MissingKeys(vec![String::from("main_class")]))?;
+        Ok(LaunchCommand::new(
+            self.build_jvm_args()?,
+            main_class,
+            self.build_game_args()?,
+        ))
+    }
+    /// Arguments for java, `extra` are user jvm options
+    pub fn build(&self, extra: Vec<String>) -> Result<Vec<String>, FillingError> {
+        Ok(self.launch()?.with_jvm_args(extra).args())
+    }
+    /// Copy-pasteable command line, the arguments passed on spawn are never quoted
+    pub fn render(
+        &self,
+        java: &str,
+        extra: Vec<String>,
+        shell: Shell,
+    ) -> Result<String, FillingError> {
+        Ok(shell.render(java, &self.build(extra)?))
+    }
+    pub fn spawn(
+        &self,
+        options: &ProcessOptions,
+        extra: Vec<String>,
+    ) -> Result<GameProcess, CommandError> {
+        options.spawn(&self.build(extra)?)
+    }
+    pub fn execute(&self, java: String, extra: Vec<String>) -> Result<GameProcess, CommandError> {
+        self.spawn(&ProcessOptions::new(&java), extra)
+    }
+}
+
+/// Filled launch arguments split in the sections java expects them
+#[derive(Debug, Clone, Default)]
+pub struct LaunchCommand {
+    pub jvm: Vec<String>,
+    pub main_class: String,
+    pub game: Vec<String>,
+    /// Options of the user, they replace the version ones with the same key
+    pub user_jvm: Vec<String>,
+    pub extra_game: Vec<String>,
+    /// File the jvm options are written to, passed as `@file`
+    pub argfile: Option<String>,
+}
+impl LaunchCommand {
+    pub fn new(jvm: Vec<String>, main_class: &str, game: Vec<String>) -> Self {
+        LaunchCommand {
+            jvm,
+            main_class: main_class.to_owned(),
+            game,
+            ..Default::default()
+        }
+    }
+    pub fn with_jvm_args(mut self, mut args: Vec<String>) -> Self {
+        self.user_jvm.append(&mut args);
+        self
+    }
+    /// Adds the options of the preset, jvm args added after it still override them
+    pub fn with_preset(mut self, preset: &JvmPreset, java: usize) -> Result<Self, JvmError> {
+        self.user_jvm.append(&mut preset.args(java)?);
+        Ok(self)
+    }
+    pub fn with_game_args(mut self, mut args: Vec<String>) -> Self {
+        self.extra_game.append(&mut args);
+        self
+    }
+    /// Version options without the ones overridden by the user, followed by the user ones
+    pub fn jvm_options(&self) -> Vec<String> {
+        let overridden: Vec<String> = self.user_jvm.iter().filter_map(|o| option_key(o)).collect();
+        let mut options: Vec<String> = self
+            .jvm
+            .iter()
+            .filter(|o| option_key(o).is_none_or(|key| !overridden.contains(&key)))
+            .cloned()
+            .collect();
+        // the user may repeat an option, the last one wins
+        for (index, option) in self.user_jvm.iter().enumerate() {
+            let key = option_key(option);
+            let repeated = key.is_some()
+                && self.user_jvm[index + 1..]
+                    .iter()
+                    .any(|o| option_key(o) == key);
+            if !repeated {
+                options.push(option.clone());
+            }
+        }
+        options
+    }
+    /// Moves the jvm options and classpath to an argument file when `java` reads them (9+).
+    /// A relative path is made absolute here, java resolves `@file` from its own working
+    /// directory, which is usually the game directory
+    pub fn with_argfile(mut self, path: &str, java: usize) -> Self {
+        if java >= 9 {
+            let path = std::path::absolute(path).unwrap_or_else(|_| PathBuf::from(path));
+            self.argfile = Some(path.to_string_lossy().into_owned());
+        } else {
+            debug!("JAVA {} HAS NO ARGUMENT FILES, KEEPING INLINE ARGS", java);
+        }
+        self
+    }
+    /// Jvm options in the argument file syntax, one per line
+    pub fn argfile_contents(&self) -> String {
+        let mut contents = String::new();
+        for option in self.jvm_options() {
+            contents.push_str(&quote_argfile(&option));
+            contents.push('\n');
+        }
+        contents
+    }
+    /// Writes the argument file, `spawn` does it before starting java
+    pub fn write_argfile(&self) -> Result<(), CommandError> {
+        let Some(path) = &self.argfile else {
+            return Ok(());
+        };
+        let error = |e| CommandError::ArgFileError(path.clone(), e);
+        if let Some(parent) = Path::new(path).parent() {
+            fs::create_dir_all(parent).map_err(error)?;
+        }
+        fs::write(path, self.argfile_contents()).map_err(error)
+    }
+    pub fn game_args(&self) -> Vec<String> {
+        let mut game = self.game.clone();
+        game.extend(self.extra_game.iter().cloned());
+        game
+    }
+    /// Arguments for java: jvm options, main class and game arguments
+    pub fn args(&self) -> Vec<String> {
+        let mut args = match &self.argfile {
+            Some(path) => vec![format!("@{}", path)],
+            None => self.jvm_options(),
+        };
+        args.push(self.main_class.clone());
+        args.append(&mut self.game_args());
         args
     }
+    /// The argument file is referenced, `write_argfile` has to run before pasting it
+    pub fn render(&self, java: &str, shell: Shell) -> String {
+        shell.render(java, &self.args())
+    }
+    pub fn spawn(&self, options: &ProcessOptions) -> Result<GameProcess, CommandError> {
+        self.write_argfile()?;
+        options.spawn(&self.args())
+    }
 }
-pub fn build_args(client: &Client, options: HashMap<String, bool>) -> (Vec<String>, Vec<String>) {
-    let mut game: Vec<String> = Vec::new();
-    let mut jvm: Vec<String> = Vec::new();
 
-    jvm.push(String::from("-Djava.library.path=${natives_directory}"));
-    jvm.push(String::from("-cp"));
-    jvm.push(String::from("${classpath}"));
-    jvm.push(String::from("${main_class}"));
+/// Quotes for the java launcher, escapes are only read inside quotes
+fn quote_argfile(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || "\"'\\#".contains(c)) {
+        return arg.to_owned();
+    }
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Key of the options that can only be set once, `-Xmx2G` is `-Xmx`, `-Dname=value` is `-Dname`
+fn option_key(option: &str) -> Option<String> {
+    for prefix in ["-Xmx", "-Xms", "-Xss", "-Xmn"] {
+        if option.starts_with(prefix) {
+            return Some(prefix.to_owned());
+        }
+    }
+    if let Some(property) = option.strip_prefix("-D") {
+        let name = property.split('=').next().unwrap_or(property);
+        return Some(format!("-D{}", name));
+    }
+    if let Some(flag) = option.strip_prefix("-XX:") {
+        let flag = flag.trim_start_matches(['+', '-']);
+        let name = flag.split('=').next().unwrap_or(flag);
+        return Some(format!("-XX:{}", name));
+    }
+    None
+}
+
+/// Arguments of the version, the rules are evaluated against the features
+pub fn build_args(client: &Client, features: &Features) -> (Vec<String>, Vec<String>) {
+    let options = features.options();
+    let mut game: Vec<String> = Vec::new();
+    let mut jvm: Vec<String> = vec![
+        String::from("-Djava.library.path=${natives_directory}"),
+        String::from("-cp"),
+        String::from("${classpath}"),
+    ];
 
     if let Some(args) = &client.minecraft_arguments {
         let args: Vec<String> = args.split(" ").map(|f| f.to_owned()).collect();
@@ -59,8 +243,15 @@ pub fn build_args(client: &Client, options: HashMap<String, bool>) -> (Vec<Strin
     if let Some(args) = &client.arguments {
         game = parse(&args.game, &options);
         jvm = parse(&args.jvm, &options);
-        if !jvm.last().unwrap().eq(&String::from("${main_class}")) {
-            jvm.push(String::from("${main_class}"));
+        // the main class is its own section of the launch command
+        jvm.retain(|arg| arg != "${main_class}");
+    }
+    if let Some(quick_play) = &features.quick_play
+        && !client.declares_feature(QUICK_PLAYS_SUPPORT)
+    {
+        match quick_play.legacy_args() {
+            Some(mut args) => game.append(&mut args),
+            None => warn!("QUICK PLAY {:?} NOT SUPPORTED BY {}", quick_play, client.id),
         }
     }
 
@@ -99,3 +290,119 @@ fn resolve_complex(
 
     Some(result)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jvm::HeapSize;
+
+    fn command(main_class: bool) -> Command {
+        let mut data = HashMap::from([(String::from("user"), String::from("Notch"))]);
+        if main_class {
+            data.insert(
+                String::from("main_class"),
+                String::from("net.minecraft.Main"),
+            );
+        }
+        Command::from_args(
+            vec![
+                String::from("--username"),
+                String::from("${user}"),
+                String::from("${token}"),
+            ],
+            vec![String::from("-Djava.library.path=${natives}")],
+            data,
+        )
+    }
+
+    #[test]
+    fn strict_launch_reports_every_missing_key() {
+        let Err(FillingError::MissingKeys(keys)) = command(false).launch() else {
+            panic!("missing keys not reported");
+        };
+        assert_eq!(keys, ["natives", "token", "main_class"]);
+    }
+
+    #[test]
+    fn lenient_launch_keeps_placeholders() {
+        let launch = command(true)
+            .with_mode(FillingMode::Lenient)
+            .launch()
+            .unwrap();
+        assert_eq!(launch.main_class, "net.minecraft.Main");
+        assert_eq!(launch.jvm, ["-Djava.library.path=${natives}"]);
+        assert_eq!(launch.game, ["--username", "Notch", "${token}"]);
+    }
+
+    #[test]
+    fn lenient_launch_requires_main_class() {
+        let result = command(false).with_mode(FillingMode::Lenient).launch();
+        assert!(matches!(result, Err(FillingError::MissingKeys(keys)) if keys == ["main_class"]));
+    }
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn option_keys() {
+        assert_eq!(option_key("-Xmx2G").as_deref(), Some("-Xmx"));
+        assert_eq!(option_key("-Xms512M").as_deref(), Some("-Xms"));
+        assert_eq!(
+            option_key("-Dfile.encoding=UTF-8").as_deref(),
+            Some("-Dfile.encoding")
+        );
+        assert_eq!(option_key("-Dflag").as_deref(), Some("-Dflag"));
+        assert_eq!(option_key("-XX:+UseG1GC").as_deref(), Some("-XX:UseG1GC"));
+        assert_eq!(option_key("-XX:-UseG1GC").as_deref(), Some("-XX:UseG1GC"));
+        assert_eq!(
+            option_key("-XX:MaxGCPauseMillis=200").as_deref(),
+            Some("-XX:MaxGCPauseMillis")
+        );
+        assert_eq!(option_key("-cp"), None);
+        assert_eq!(option_key("${classpath}"), None);
+    }
+
+    #[test]
+    fn user_options_override_the_version_ones() {
+        let launch = LaunchCommand::new(
+            strings(&[
+                "-Xmx1G",
+                "-Dos.name=Windows 10",
+                "-XX:+UseG1GC",
+                "-cp",
+                "a.jar",
+            ]),
+            "Main",
+            Vec::new(),
+        )
+        .with_preset(
+            &JvmPreset::new().with_max_heap(HeapSize::Mebibytes(2048)),
+            21,
+        )
+        .unwrap()
+        .with_jvm_args(strings(&["-Dos.name=Linux", "-XX:-UseG1GC", "-Xmx4G"]));
+        assert_eq!(
+            launch.jvm_options(),
+            ["-cp", "a.jar", "-Dos.name=Linux", "-XX:-UseG1GC", "-Xmx4G"]
+        );
+    }
+
+    #[test]
+    fn repeated_options_keep_the_last_one() {
+        let launch = LaunchCommand::new(Vec::new(), "Main", Vec::new())
+            .with_jvm_args(strings(&["-Xmx2G", "-verbose", "-Xmx3G", "-verbose"]));
+        assert_eq!(launch.jvm_options(), ["-verbose", "-Xmx3G", "-verbose"]);
+    }
+
+    #[test]
+    fn sections_in_order() {
+        let launch = LaunchCommand::new(strings(&["-cp", "a.jar"]), "Main", strings(&["--demo"]))
+            .with_jvm_args(strings(&["-Xmx2G"]))
+            .with_game_args(strings(&["--width", "800"]));
+        assert_eq!(
+            launch.args(),
+            ["-cp", "a.jar", "-Xmx2G", "Main", "--demo", "--width", "800"]
+        );
+    }
+}
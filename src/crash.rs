@@ -0,0 +1,461 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use tracing::debug;
+
+use crate::api::client::Client;
+
+#[derive(Debug, Clone, Default)]
+pub struct CrashReport {
+    pub time: Option<String>,
+    pub description: String,
+    pub stack_trace: Vec<String>,
+    pub mods: Vec<String>,
+    pub system_details: HashMap<String, String>,
+    pub raw: String,
+}
+impl CrashReport {
+    pub fn load(path: &Path) -> Result<Self, std::io::Error> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+    /// Newest report in `<game_directory>/crash-reports`, written after `since` if given
+    pub fn find_latest(
+        game_directory: &str,
+        since: Option<SystemTime>,
+    ) -> Result<Option<PathBuf>, std::io::Error> {
+        let dir = Path::new(game_directory).join("crash-reports");
+        if !dir.exists() {
+            return Ok(None);
+        }
+        let mut latest: Option<(SystemTime, PathBuf)> = None;
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !name.starts_with("crash-") || !name.ends_with(".txt") {
+                continue;
+            }
+            let modified = entry.metadata()?.modified()?;
+            if since.is_some_and(|since| modified < since) {
+                continue;
+            }
+            if latest.as_ref().is_none_or(|(time, _)| modified > *time) {
+                latest = Some((modified, entry.path()));
+            }
+        }
+        debug!("LATEST CRASH REPORT {:?}", latest);
+        Ok(latest.map(|(_, path)| path))
+    }
+    pub fn parse(text: &str) -> Self {
+        let mut report = CrashReport {
+            raw: text.to_owned(),
+            ..Default::default()
+        };
+        let lines: Vec<&str> = text.lines().collect();
+
+        let mut index = 0;
+        while index < lines.len() {
+            let line = lines[index];
+            if let Some(time) = line.strip_prefix("Time: ") {
+                report.time = Some(time.trim().to_owned());
+            } else if let Some(description) = line.strip_prefix("Description: ") {
+                report.description = description.trim().to_owned();
+                index += 1;
+                while index < lines.len() && lines[index].trim().is_empty() {
+                    index += 1;
+                }
+                while index < lines.len() && !lines[index].trim().is_empty() {
+                    report.stack_trace.push(lines[index].to_owned());
+                    index += 1;
+                }
+                continue;
+            }
+            index += 1;
+        }
+        // without a header, e.g. a game log, the first exception is the stack trace
+        if report.stack_trace.is_empty()
+            && let Some(start) = lines.iter().position(|l| is_exception(l))
+        {
+            report.stack_trace = lines[start..]
+                .iter()
+                .take_while(|l| !l.trim().is_empty())
+                .map(|l| l.to_string())
+                .collect();
+        }
+
+        report.parse_details(&lines);
+        report
+    }
+    fn parse_details(&mut self, lines: &[&str]) {
+        let Some(start) = lines
+            .iter()
+            .position(|l| l.trim() == "-- System Details --")
+        else {
+            return;
+        };
+        let mut key: Option<String> = None;
+        for line in &lines[start + 1..] {
+            if line.trim().is_empty() || line.starts_with("-- ") {
+                break;
+            }
+            let depth = line.chars().take_while(|c| *c == '\t').count();
+            let content = line.trim();
+            if depth >= 2 || (depth == 0 && content.starts_with('|')) {
+                // continuation of the last key, mod loaders list their mods here
+                if let Some(key) = &key {
+                    let value = self.system_details.entry(key.clone()).or_default();
+                    if !value.is_empty() {
+                        value.push('\n');
+                    }
+                    value.push_str(content);
+                    if is_mod_key(key) {
+                        self.mods.push(content.to_owned());
+                    }
+                }
+            } else if let Some((k, v)) = content.split_once(':') {
+                key = Some(k.trim().to_owned());
+                self.system_details
+                    .insert(k.trim().to_owned(), v.trim().to_owned());
+            }
+        }
+    }
+    /// Java major version the game was running on, from the system details
+    pub fn java_major(&self) -> Option<usize> {
+        let version = self.system_details.get("Java Version")?;
+        let version = version.split(',').next()?.trim();
+        parse_java_major(version)
+    }
+    pub fn diagnose(&self, client: Option<&Client>) -> Vec<Diagnosis> {
+        self.diagnose_with(&rules(), client)
+    }
+    pub fn diagnose_with(&self, rules: &[CrashRule], client: Option<&Client>) -> Vec<Diagnosis> {
+        rules
+            .iter()
+            .filter_map(|rule| {
+                (rule.check)(self, client).map(|hint| Diagnosis {
+                    cause: rule.cause.clone(),
+                    hint,
+                })
+            })
+            .collect()
+    }
+}
+
+fn is_exception(line: &str) -> bool {
+    let head = line.split(':').next().unwrap_or("");
+    !head.contains(' ') && (head.ends_with("Exception") || head.ends_with("Error"))
+}
+
+fn is_mod_key(key: &str) -> bool {
+    matches!(
+        key,
+        "Fabric Mods" | "Mod List" | "Loaded mods" | "Mods" | "FML"
+    )
+}
+
+/// Parses `1.8.0_382`, `17.0.8` or `21` into the major version
+pub fn parse_java_major(version: &str) -> Option<usize> {
+    let mut parts = version.split(['.', '_', '+', '-']);
+    let first: usize = parts.next()?.parse().ok()?;
+    if first == 1 {
+        return parts.next()?.parse().ok();
+    }
+    Some(first)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CrashCause {
+    JavaVersion,
+    MissingNatives,
+    OutOfMemory,
+    ModConflict,
+    Other(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnosis {
+    pub cause: CrashCause,
+    pub hint: String,
+}
+
+/// Known cause of crashes, `check` returns the hint when the report matches
+pub struct CrashRule {
+    pub cause: CrashCause,
+    pub check: fn(&CrashReport, Option<&Client>) -> Option<String>,
+}
+
+pub fn rules() -> Vec<CrashRule> {
+    vec![
+        CrashRule {
+            cause: CrashCause::JavaVersion,
+            check: check_java_version,
+        },
+        CrashRule {
+            cause: CrashCause::MissingNatives,
+            check: check_natives,
+        },
+        CrashRule {
+            cause: CrashCause::OutOfMemory,
+            check: check_memory,
+        },
+        CrashRule {
+            cause: CrashCause::ModConflict,
+            check: check_mods,
+        },
+    ]
+}
+
+fn check_java_version(report: &CrashReport, client: Option<&Client>) -> Option<String> {
+    // UnsupportedClassVersionError: ... class file version 65.0
+    if let Some(index) = report.raw.find("class file version ") {
+        let rest = &report.raw[index + "class file version ".len()..];
+        let version: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+        if let Some(major) = version
+            .parse::<usize>()
+            .ok()
+            .and_then(|v| v.checked_sub(44))
+        {
+            return Some(format!(
+                "Classes were compiled for Java {}, launch the game with Java {} or newer",
+                major, major
+            ));
+        }
+    }
+    let required = client.map(|c| c.java()).filter(|v| *v != 0)?;
+    let running = report.java_major()?;
+    let id = &client.unwrap().id;
+    if running < required {
+        return Some(format!(
+            "The game ran on Java {} but version {} requires Java {}",
+            running, id, required
+        ));
+    }
+    // versions before 1.17 break on newer Java, as `Client::check_java` warns
+    if running > required && required < 16 {
+        return Some(format!(
+            "The game ran on Java {} but version {} requires Java {}, newer Java may break it",
+            running, id, required
+        ));
+    }
+    None
+}
+
+fn check_natives(report: &CrashReport, _: Option<&Client>) -> Option<String> {
+    let raw = &report.raw;
+    if raw.contains("in java.library.path") || raw.contains("UnsatisfiedLinkError") {
+        return Some(String::from(
+            "Native libraries could not be loaded, check that natives were extracted to the \
+             directory passed as natives_directory",
+        ));
+    }
+    if raw.contains("GLFW error 65542") || raw.contains("Failed to locate library: liblwjgl") {
+        return Some(String::from(
+            "LWJGL natives are missing or do not match the platform",
+        ));
+    }
+    None
+}
+
+fn check_memory(report: &CrashReport, _: Option<&Client>) -> Option<String> {
+    if report.raw.contains("java.lang.OutOfMemoryError") {
+        let memory = report
+            .system_details
+            .get("Memory")
+            .map(|m| format!(" ({})", m))
+            .unwrap_or_default();
+        return Some(format!(
+            "The game ran out of memory{}, increase the heap with -Xmx",
+            memory
+        ));
+    }
+    None
+}
+
+fn check_mods(report: &CrashReport, _: Option<&Client>) -> Option<String> {
+    let raw = &report.raw;
+    let markers = [
+        "DuplicateModsFoundException",
+        "Found duplicate mods",
+        "ModResolutionException",
+        "Incompatible mod set",
+        "MixinApplyError",
+        "Mixin apply failed",
+        "mixin.transformer.throwables.MixinTransformerError",
+    ];
+    let marker = markers.iter().find(|m| raw.contains(**m))?;
+    let culprits: Vec<&String> = report
+        .mods
+        .iter()
+        .filter(|m| {
+            let id = m.split([':', ' ']).next().unwrap_or("");
+            !id.is_empty() && report.stack_trace.iter().any(|l| l.contains(id))
+        })
+        .collect();
+    if culprits.is_empty() {
+        return Some(format!(
+            "Mod loading failed ({}), remove duplicated or incompatible mods",
+            marker
+        ));
+    }
+    Some(format!(
+        "Mod loading failed ({}), mods involved: {}",
+        marker,
+        culprits
+            .iter()
+            .map(|m| m.as_str())
+            .collect::<Vec<&str>>()
+            .join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPORT: &str = "---- Minecraft Crash Report ----
+// Who set us up the TNT?
+
+Time: 2024-08-08 12:00:00
+Description: Mod loading failed
+
+java.lang.RuntimeException: Mixin apply failed
+\tat org.spongepowered.asm.mixin.transformer.MixinProcessor.applyMixins(MixinProcessor.java:392)
+\tat com.example.sodium.Renderer.init(Renderer.java:10)
+
+A detailed walkthrough of the error, its code path and all known details is as follows:
+
+-- System Details --
+Details:
+\tMinecraft Version: 1.20.1
+\tJava Version: 17.0.8, Eclipse Adoptium
+\tMemory: 123 bytes (0 MiB) / 456 bytes (0 MiB) up to 789 bytes (0 MiB)
+\tFabric Mods: 
+\t\tsodium: Sodium 0.5.3
+\t\tlithium: Lithium 0.11.2
+";
+
+    fn client(id: &str, java: u32) -> Client {
+        serde_json::from_str(&format!(
+            r#"{{"id":"{}","libraries":[],"mainClass":"M","releaseTime":"","time":"",
+                "type":"release","javaVersion":{{"component":"","majorVersion":{}}}}}"#,
+            id, java
+        ))
+        .unwrap()
+    }
+
+    fn report(java: &str) -> CrashReport {
+        CrashReport::parse(&format!(
+            "Description: Crash\n\n-- System Details --\nDetails:\n\tJava Version: {}, Oracle\n",
+            java
+        ))
+    }
+
+    fn causes(report: &CrashReport, client: Option<&Client>) -> Vec<CrashCause> {
+        report
+            .diagnose(client)
+            .into_iter()
+            .map(|diagnosis| diagnosis.cause)
+            .collect()
+    }
+
+    #[test]
+    fn parses_the_report() {
+        let report = CrashReport::parse(REPORT);
+        assert_eq!(report.time.as_deref(), Some("2024-08-08 12:00:00"));
+        assert_eq!(report.description, "Mod loading failed");
+        assert_eq!(report.stack_trace.len(), 3);
+        assert_eq!(
+            report.stack_trace[0],
+            "java.lang.RuntimeException: Mixin apply failed"
+        );
+        assert_eq!(
+            report
+                .system_details
+                .get("Minecraft Version")
+                .map(|v| v.as_str()),
+            Some("1.20.1")
+        );
+        assert_eq!(
+            report.mods,
+            ["sodium: Sodium 0.5.3", "lithium: Lithium 0.11.2"]
+        );
+        assert_eq!(report.java_major(), Some(17));
+    }
+
+    #[test]
+    fn stack_trace_of_a_log_without_header() {
+        let report = CrashReport::parse(
+            "[12:00:00] [main/INFO]: Loading\njava.lang.IllegalStateException: boom\n\tat a.B.c(B.java:1)\n\n",
+        );
+        assert_eq!(
+            report.stack_trace,
+            [
+                "java.lang.IllegalStateException: boom",
+                "\tat a.B.c(B.java:1)"
+            ]
+        );
+    }
+
+    #[test]
+    fn java_major_of_version_strings() {
+        assert_eq!(parse_java_major("1.8.0_382"), Some(8));
+        assert_eq!(parse_java_major("17.0.8"), Some(17));
+        assert_eq!(parse_java_major("21"), Some(21));
+        assert_eq!(parse_java_major("21-ea+35"), Some(21));
+        assert_eq!(parse_java_major("unknown"), None);
+    }
+
+    #[test]
+    fn mod_conflict_names_the_mods_in_the_stack_trace() {
+        let diagnosis = CrashReport::parse(REPORT).diagnose(None);
+        let conflict = diagnosis
+            .iter()
+            .find(|d| d.cause == CrashCause::ModConflict)
+            .unwrap();
+        assert!(conflict.hint.contains("sodium: Sodium 0.5.3"));
+        assert!(!conflict.hint.contains("lithium"));
+    }
+
+    #[test]
+    fn java_too_old_or_too_new() {
+        let old = client("1.20.1", 17);
+        assert_eq!(
+            causes(&report("1.8.0_382"), Some(&old)),
+            [CrashCause::JavaVersion]
+        );
+        assert!(causes(&report("21.0.2"), Some(&old)).is_empty());
+        let legacy = client("1.12.2", 8);
+        assert_eq!(
+            causes(&report("17.0.8"), Some(&legacy)),
+            [CrashCause::JavaVersion]
+        );
+        assert!(causes(&report("1.8.0_382"), Some(&legacy)).is_empty());
+        let snapshot = client("1.17.1", 16);
+        assert!(causes(&report("17.0.8"), Some(&snapshot)).is_empty());
+        // without the version the running Java isn't compared
+        assert!(causes(&report("1.8.0_382"), None).is_empty());
+    }
+
+    #[test]
+    fn class_file_version() {
+        let report = CrashReport::parse(
+            "java.lang.UnsupportedClassVersionError: a/B has been compiled by a more recent \
+             version of the Java Runtime (class file version 65.0)",
+        );
+        let diagnosis = report.diagnose(None);
+        assert_eq!(diagnosis[0].cause, CrashCause::JavaVersion);
+        assert!(diagnosis[0].hint.contains("Java 21"));
+    }
+
+    #[test]
+    fn natives_and_memory() {
+        let natives =
+            CrashReport::parse("java.lang.UnsatisfiedLinkError: no lwjgl in java.library.path");
+        assert_eq!(causes(&natives, None), [CrashCause::MissingNatives]);
+        let memory = CrashReport::parse("java.lang.OutOfMemoryError: Java heap space");
+        assert_eq!(causes(&memory, None), [CrashCause::OutOfMemory]);
+    }
+}
@@ -14,15 +14,21 @@ pub enum FetchError {
     SerdeError(#[from] serde_json::Error),
     #[error("Error canonicalizing path {0}")]
     CanonicalizingError(String),
+    #[error("HTTP error")]
+    HttpError(#[from] HttpError),
+    #[error("Hash mismatch for {0}")]
+    HashMismatch(String),
+    #[error("Size mismatch for {0}, expected {1} got {2}")]
+    SizeMismatch(String, u64, u64),
+    #[error("Unknown java distribution {0}")]
+    UnknownDistribution(String),
 }
 #[derive(Error, Debug)]
 pub enum FillingError {
-    #[error("Text hasn't pattron")]
-    NoPattron(),
-    #[error("Malformed text")]
-    Malformed(),
-    #[error("No key found {0}")]
-    NoKeyFound(String)
+    #[error("Malformed text {0}")]
+    Malformed(String),
+    #[error("No key found {0:?}")]
+    MissingKeys(Vec<String>),
 }
 #[derive(Error, Debug)]
 pub enum ArtifactError {
@@ -42,5 +48,97 @@ pub enum ClassifierError {
 #[derive(Error, Debug)]
 pub enum CommandError {
     #[error("Error executing command")]
-    ExecError(#[from] std::io::Error)
+    ExecError(#[from] std::io::Error),
+    #[error("Error writing argument file {0}")]
+    ArgFileError(String, #[source] std::io::Error),
+    #[error("Error filling arguments")]
+    FillingError(#[from] FillingError),
+}
+
+#[derive(Error, Debug)]
+pub enum HttpError {
+    #[error("Invalid url {0}")]
+    InvalidUrl(String),
+    #[error("Request failed: {0}")]
+    Request(String),
+    #[error("Request failed with status {0}: {1}")]
+    Status(u16, String),
+    #[error("Deserialization Error")]
+    SerdeError(#[from] serde_json::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum AuthError {
+    #[error("HTTP error")]
+    HttpError(#[from] HttpError),
+    #[error("Deserialization Error")]
+    SerdeError(#[from] serde_json::Error),
+    #[error("Authorization declined by the user")]
+    Declined(),
+    #[error("Device code expired")]
+    Expired(),
+    #[error("Account has no Xbox profile")]
+    NoXboxAccount(),
+    #[error("Xbox Live is not available in the account country")]
+    XboxUnavailable(),
+    #[error("Child account must be added to a family")]
+    ChildAccount(),
+    #[error("Account doesn't own the game")]
+    NotOwned(),
+    #[error("Unexpected response from {0}")]
+    InvalidResponse(String),
+    #[error("Invalid username {0}")]
+    InvalidUsername(String),
+    #[error("Invalid credentials: {0}")]
+    InvalidCredentials(String),
+    #[error("Account {0} not found")]
+    AccountNotFound(String),
+    #[error("No auth provider configured for {0} accounts")]
+    NoProvider(String),
+    #[error("IO error")]
+    IOError(#[from] std::io::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum JvmError {
+    #[error("Invalid heap size {0}")]
+    InvalidHeap(String),
+    #[error("System memory unavailable")]
+    MemoryUnavailable(),
+    #[error("{0} requires Java {1}, found Java {2}")]
+    Unsupported(String, usize, usize),
+    #[error("IO error")]
+    IOError(#[from] std::io::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum JavaError {
+    #[error("No java installed at {0}")]
+    NotInstalled(String),
+    #[error("{0} is not executable")]
+    NotExecutable(String),
+    #[error("Java {1} installed, Java {0} required")]
+    VersionMismatch(usize, usize),
+    #[error("Incomplete install, {0} is left over")]
+    Incomplete(String),
+    #[error("Incomplete install, {0} is missing")]
+    MissingFile(String),
+    #[error("Fetch error")]
+    FetchError(#[from] FetchError),
+    #[error("IO error")]
+    IOError(#[from] std::io::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum StoreError {
+    #[error("Version {0} is not registered")]
+    NotRegistered(String),
+    #[error("No version is registered, refusing to collect every file")]
+    NoReferences(),
+    #[error("Store is locked by {0}, remove it if no launcher is running")]
+    Locked(String),
+    #[error("IO error")]
+    IOError(#[from] std::io::Error),
+    #[error("Deserialization Error")]
+    SerdeError(#[from] serde_json::Error),
 }
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+pub const DEMO_USER: &str = "is_demo_user";
+pub const CUSTOM_RESOLUTION: &str = "has_custom_resolution";
+pub const QUICK_PLAYS_SUPPORT: &str = "has_quick_plays_support";
+pub const QUICK_PLAY_SINGLEPLAYER: &str = "is_quick_play_singleplayer";
+pub const QUICK_PLAY_MULTIPLAYER: &str = "is_quick_play_multiplayer";
+pub const QUICK_PLAY_REALMS: &str = "is_quick_play_realms";
+
+#[derive(Debug, Clone, Copy)]
+pub struct WindowSettings {
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone)]
+pub enum QuickPlay {
+    /// Name of the world folder in `saves`
+    Singleplayer(String),
+    /// Server address, `host` or `host:port`
+    Multiplayer(String),
+    /// Id of the realm
+    Realms(String),
+}
+
+impl QuickPlay {
+    /// `--server` and `--port` for versions without quick play, only multiplayer has them
+    pub fn legacy_args(&self) -> Option<Vec<String>> {
+        let QuickPlay::Multiplayer(address) = self else {
+            return None;
+        };
+        let (host, port) = split_address(address);
+        Some(vec![
+            String::from("--server"),
+            host.to_owned(),
+            String::from("--port"),
+            port.to_owned(),
+        ])
+    }
+}
+
+/// Host and port of `host`, `host:port` or `[ipv6]:port`
+fn split_address(address: &str) -> (&str, &str) {
+    if let Some(rest) = address.strip_prefix('[')
+        && let Some((host, port)) = rest.split_once(']')
+    {
+        return (host, port.strip_prefix(':').unwrap_or("25565"));
+    }
+    match address.split_once(':') {
+        Some((host, port)) if !port.contains(':') => (host, port),
+        _ => (address, "25565"),
+    }
+}
+
+/// Features the argument rules of the version are evaluated against
+#[derive(Debug, Clone, Default)]
+pub struct Features {
+    pub demo: bool,
+    pub resolution: Option<WindowSettings>,
+    pub quick_play: Option<QuickPlay>,
+    /// File the game writes the quick play history to, enables `has_quick_plays_support`
+    pub quick_play_path: Option<String>,
+    /// Features unknown to this crate, by their name in the version json
+    pub custom: HashMap<String, bool>,
+}
+impl Features {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_demo(mut self, demo: bool) -> Self {
+        self.demo = demo;
+        self
+    }
+    pub fn with_resolution(mut self, width: u32, height: u32) -> Self {
+        self.resolution = Some(WindowSettings { width, height });
+        self
+    }
+    pub fn with_quick_play(mut self, quick_play: QuickPlay) -> Self {
+        self.quick_play = Some(quick_play);
+        self
+    }
+    pub fn with_quick_play_path(mut self, path: &str) -> Self {
+        self.quick_play_path = Some(path.to_owned());
+        self
+    }
+    pub fn with_feature(mut self, name: &str, enabled: bool) -> Self {
+        self.custom.insert(name.to_owned(), enabled);
+        self
+    }
+    /// Value of every feature by its name in the version json
+    pub fn options(&self) -> HashMap<String, bool> {
+        let mut options = HashMap::new();
+        let quick_play = self.quick_play.as_ref();
+        options.insert(DEMO_USER.to_owned(), self.demo);
+        options.insert(CUSTOM_RESOLUTION.to_owned(), self.resolution.is_some());
+        options.insert(
+            QUICK_PLAYS_SUPPORT.to_owned(),
+            self.quick_play_path.is_some(),
+        );
+        options.insert(
+            QUICK_PLAY_SINGLEPLAYER.to_owned(),
+            matches!(quick_play, Some(QuickPlay::Singleplayer(_))),
+        );
+        options.insert(
+            QUICK_PLAY_MULTIPLAYER.to_owned(),
+            matches!(quick_play, Some(QuickPlay::Multiplayer(_))),
+        );
+        options.insert(
+            QUICK_PLAY_REALMS.to_owned(),
+            matches!(quick_play, Some(QuickPlay::Realms(_))),
+        );
+        options.extend(self.custom.clone());
+        options
+    }
+    /// Placeholders the enabled features need
+    pub fn apply(&self, data: &mut HashMap<String, String>) {
+        if let Some(resolution) = &self.resolution {
+            data.insert("resolution_width".to_owned(), resolution.width.to_string());
+            data.insert(
+                "resolution_height".to_owned(),
+                resolution.height.to_string(),
+            );
+        }
+        if let Some(path) = &self.quick_play_path {
+            data.insert("quickPlayPath".to_owned(), path.clone());
+        }
+        match &self.quick_play {
+            Some(QuickPlay::Singleplayer(world)) => {
+                data.insert("quickPlaySingleplayer".to_owned(), world.clone());
+            }
+            Some(QuickPlay::Multiplayer(server)) => {
+                data.insert("quickPlayMultiplayer".to_owned(), server.clone());
+            }
+            Some(QuickPlay::Realms(realm)) => {
+                data.insert("quickPlayRealms".to_owned(), realm.clone());
+            }
+            None => {}
+        }
+    }
+    pub fn data(&self) -> HashMap<String, String> {
+        let mut data = HashMap::new();
+        self.apply(&mut data);
+        data
+    }
+}
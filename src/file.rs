@@ -9,8 +9,8 @@ pub enum Type {
 impl Type {
     pub fn file(&self, client: &Client) -> JarFile {
         match self {
-            Type::Client => return client.downloads.client.clone(),
-            Type::Server => return client.downloads.server.clone(),
+            Type::Client => client.downloads.client.clone(),
+            Type::Server => client.downloads.server.clone(),
         }
     }
 }
@@ -0,0 +1,196 @@
+use serde::{Serialize, de::DeserializeOwned};
+use surf::{
+    RequestBuilder, Url,
+    http::{Method, mime},
+};
+use tracing::debug;
+
+use crate::errors::HttpError;
+
+pub(crate) struct Response {
+    pub status: u16,
+    pub body: String,
+    /// Target of a redirect
+    pub location: Option<String>,
+}
+impl Response {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, HttpError> {
+        Ok(serde_json::from_str(&self.body)?)
+    }
+    /// Parses the body, failing with the status when the request wasn't successful
+    pub fn success_json<T: DeserializeOwned>(&self) -> Result<T, HttpError> {
+        if !self.is_success() {
+            return Err(HttpError::Status(self.status, self.body.clone()));
+        }
+        self.json()
+    }
+}
+
+const MAX_REDIRECTS: usize = 10;
+
+fn request(method: Method, url: &str) -> Result<RequestBuilder, HttpError> {
+    let url = Url::parse(url).map_err(|_| HttpError::InvalidUrl(url.to_owned()))?;
+    Ok(RequestBuilder::new(method, url).header("Accept", "application/json"))
+}
+
+fn send(request: RequestBuilder) -> Result<Response, HttpError> {
+    smol::block_on(async {
+        let request = request.build();
+        debug!("HTTP {} {}", request.method(), request.url());
+        let mut response = surf::Client::new()
+            .send(request)
+            .await
+            .map_err(|e| HttpError::Request(e.to_string()))?;
+        let body = response
+            .body_string()
+            .await
+            .map_err(|e| HttpError::Request(e.to_string()))?;
+        Ok(Response {
+            status: response.status().into(),
+            body,
+            location: response
+                .header("Location")
+                .map(|value| value.as_str().to_owned()),
+        })
+    })
+}
+
+/// Get request following up to `MAX_REDIRECTS` redirects, the bearer token is only sent
+/// to the origin of the first url
+pub(crate) fn get(url: &str, bearer: Option<&str>) -> Result<Response, HttpError> {
+    let mut url = Url::parse(url).map_err(|_| HttpError::InvalidUrl(url.to_owned()))?;
+    let mut bearer = bearer;
+    for _ in 0..MAX_REDIRECTS {
+        let mut request = request(Method::Get, url.as_str())?;
+        if let Some(token) = bearer {
+            request = request.header("Authorization", format!("Bearer {}", token));
+        }
+        let response = send(request)?;
+        match (&response.location, response.status) {
+            (Some(location), 301 | 302 | 303 | 307 | 308) => {
+                let next = url
+                    .join(location)
+                    .map_err(|_| HttpError::InvalidUrl(location.clone()))?;
+                if bearer.is_some() && next.origin() != url.origin() {
+                    debug!(
+                        "HTTP REDIRECT TO {} WITHOUT AUTHORIZATION",
+                        next.origin().ascii_serialization()
+                    );
+                    bearer = None;
+                }
+                url = next;
+            }
+            _ => return Ok(response),
+        }
+    }
+    Err(HttpError::Request(format!(
+        "too many redirects for {}",
+        url
+    )))
+}
+
+pub(crate) fn post_json<T: Serialize>(url: &str, body: &T) -> Result<Response, HttpError> {
+    let body = serde_json::to_string(body)?;
+    send(
+        request(Method::Post, url)?
+            .content_type(mime::JSON)
+            .body_string(body),
+    )
+}
+
+pub(crate) fn post_form(url: &str, form: &[(&str, &str)]) -> Result<Response, HttpError> {
+    let body = url::form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(form)
+        .finish();
+    send(
+        request(Method::Post, url)?
+            .content_type(mime::FORM)
+            .body_string(body),
+    )
+}
+
+/// Local HTTP/1.1 server standing in for the remote apis in tests
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::{TcpListener, TcpStream},
+        thread,
+    };
+
+    pub struct Request {
+        pub method: String,
+        /// Path with the query
+        pub path: String,
+        pub headers: Vec<(String, String)>,
+        pub body: String,
+    }
+    impl Request {
+        pub fn header(&self, name: &str) -> Option<&str> {
+            self.headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        }
+    }
+
+    /// Answers every request with the status and json body of the handler, returns the
+    /// base url. The server lives until the test process exits
+    pub fn serve(handler: impl Fn(&Request) -> (u16, String) + Send + 'static) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Some(request) = read_request(&stream) {
+                    let (status, body) = handler(&request);
+                    let _ = write!(
+                        &stream,
+                        "HTTP/1.1 {} STATUS\r\nContent-Type: application/json\r\n\
+                         Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                }
+            }
+        });
+        url
+    }
+
+    fn read_request(stream: &TcpStream) -> Option<Request> {
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let mut parts = line.split_whitespace();
+        let method = parts.next()?.to_owned();
+        let path = parts.next()?.to_owned();
+        let mut headers = Vec::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).ok()?;
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            let (key, value) = line.split_once(':')?;
+            headers.push((key.trim().to_owned(), value.trim().to_owned()));
+        }
+        let mut request = Request {
+            method,
+            path,
+            headers,
+            body: String::new(),
+        };
+        let length: usize = request
+            .header("Content-Length")
+            .and_then(|length| length.parse().ok())
+            .unwrap_or(0);
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).ok()?;
+        request.body = String::from_utf8_lossy(&body).into_owned();
+        Some(request)
+    }
+}
@@ -0,0 +1,341 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use dwldutil::DLFile;
+use serde::Deserialize;
+use tracing::{debug, warn};
+
+use crate::{
+    errors::FetchError,
+    http,
+    java::{JavaProvider, JavaRelease, JavaTarget},
+    os::system::OperatingSystem,
+};
+
+pub const ADOPTIUM_API: &str = "https://api.adoptium.net";
+
+#[derive(Deserialize)]
+struct AdoptiumAsset {
+    binary: AdoptiumBinary,
+    release_name: String,
+}
+#[derive(Deserialize)]
+struct AdoptiumBinary {
+    image_type: String,
+    package: AdoptiumPackage,
+}
+#[derive(Deserialize)]
+struct AdoptiumPackage {
+    checksum: String,
+    link: String,
+    size: u64,
+}
+#[derive(Deserialize)]
+struct AdoptiumReleases {
+    available_releases: Vec<usize>,
+}
+
+/// Latest GA Temurin builds from the Adoptium assets api
+pub struct Temurin {
+    pub base_url: String,
+    /// Builds used when the api can't be reached, by `<os>-<arch>-<version>`
+    pub fallback: HashMap<String, JavaRelease>,
+    resolved: Mutex<HashMap<String, JavaRelease>>,
+}
+impl Default for Temurin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Temurin {
+    pub fn new() -> Self {
+        let mut fallback = HashMap::new();
+        fallback.insert(
+            String::from("windows-x86_64-21"),
+            JavaRelease {
+                version: 21,
+                id: Some(String::from("jdk-21.0.7+6-jre")),
+                url: String::from("https://github.com/adoptium/temurin21-binaries/releases/download/jdk-21.0.7%2B6/OpenJDK21U-jre_x64_windows_hotspot_21.0.7_6.zip"),
+                sha256: String::from("b2850a96293048ed3020f8bfca2d92a785ae9bf80c7d96bbfe3ec4ccf45aef98"),
+                size: Some(48875360),
+            },
+        );
+        fallback.insert(
+            String::from("linux-x86_64-21"),
+            JavaRelease {
+                version: 21,
+                id: Some(String::from("jdk-21.0.7+6-jre")),
+                url: String::from("https://github.com/adoptium/temurin21-binaries/releases/download/jdk-21.0.7%2B6/OpenJDK21U-jre_x64_linux_hotspot_21.0.7_6.tar.gz"),
+                sha256: String::from("6d48379e00d47e6fdd417e96421e973898ac90765ea8ff2d09ae0af6d5d6a1c6"),
+                size: Some(51863597),
+            },
+        );
+        fallback.insert(
+            String::from("windows-x86_64-17"),
+            JavaRelease {
+                version: 17,
+                id: Some(String::from("jdk-17.0.9+9-jre")),
+                url: String::from("https://github.com/adoptium/temurin17-binaries/releases/download/jdk-17.0.9%2B9.1/OpenJDK17U-jre_x64_windows_hotspot_17.0.9_9.zip"),
+                sha256: String::from("6c491d6f8c28c6f451f08110a30348696a04b009f8c58592191046e0fab1477b"),
+                size: Some(43447242),
+            },
+        );
+        fallback.insert(
+            String::from("linux-x86_64-17"),
+            JavaRelease {
+                version: 17,
+                id: Some(String::from("jdk-17.0.9+9-jre")),
+                url: String::from("https://github.com/adoptium/temurin17-binaries/releases/download/jdk-17.0.9%2B9/OpenJDK17U-jre_x64_linux_hotspot_17.0.9_9.tar.gz"),
+                sha256: String::from("c37f729200b572884b8f8e157852c739be728d61d9a1da0f920104876d324733"),
+                size: Some(46280224),
+            },
+        );
+        fallback.insert(
+            String::from("windows-x86_64-8"),
+            JavaRelease {
+                version: 8,
+                id: Some(String::from("jdk8u452-b09-jre")),
+                url: String::from("https://github.com/adoptium/temurin8-binaries/releases/download/jdk8u452-b09/OpenJDK8U-jre_x64_windows_hotspot_8u452b09.zip"),
+                sha256: String::from("802b1277505308290b6f00d8addde93e537d559cea1c826752d0cc46e7b58a5f"),
+                size: Some(40652901),
+            },
+        );
+        fallback.insert(
+            String::from("linux-x86_64-8"),
+            JavaRelease {
+                version: 8,
+                id: Some(String::from("jdk8u452-b09-jre")),
+                url: String::from("https://github.com/adoptium/temurin8-binaries/releases/download/jdk8u452-b09/OpenJDK8U-jre_x64_linux_hotspot_8u452b09.tar.gz"),
+                sha256: String::from("0c76f94e1b400a4da932a3f581b0788af2101819083184f40a6c76ac9b97081f"),
+                size: Some(41420532),
+            },
+        );
+        Temurin {
+            base_url: ADOPTIUM_API.to_owned(),
+            fallback,
+            resolved: Mutex::new(HashMap::new()),
+        }
+    }
+    pub fn with_base_url(mut self, url: &str) -> Self {
+        self.base_url = url.trim_end_matches('/').to_owned();
+        self
+    }
+    fn key(version: usize, target: &JavaTarget) -> String {
+        format!("{}-{}-{}", target.os.name(), target.arch, version)
+    }
+    /// Latest JRE of the major version for the target
+    pub fn latest(&self, version: usize, target: &JavaTarget) -> Result<JavaRelease, FetchError> {
+        let os = match target.os {
+            OperatingSystem::Linux => "linux",
+            OperatingSystem::Windows => "windows",
+            OperatingSystem::MacOS => "mac",
+            OperatingSystem::Other => return Err(FetchError::OsUnsupported()),
+        };
+        let arch = match target.arch.as_str() {
+            "x86_64" => "x64",
+            "x86" => "x86",
+            "aarch64" => "aarch64",
+            "arm" => "arm",
+            _ => return Err(FetchError::OsUnsupported()),
+        };
+        let url = format!(
+            "{}/v3/assets/latest/{}/hotspot?architecture={}&image_type=jre&os={}&vendor=eclipse",
+            self.base_url, version, arch, os
+        );
+        let assets: Vec<AdoptiumAsset> = http::get(&url, None)?.success_json()?;
+        let asset = assets
+            .into_iter()
+            .find(|asset| asset.binary.image_type == "jre")
+            .ok_or(FetchError::UrlNotFound(version.to_string()))?;
+        debug!("ADOPTIUM {} FOR JAVA {}", asset.release_name, version);
+        let package = asset.binary.package;
+        Ok(JavaRelease {
+            version,
+            id: Some(format!("{}-jre", asset.release_name)),
+            url: package.link,
+            sha256: package.checksum,
+            size: Some(package.size),
+        })
+    }
+    /// Release of the major version, from the api or the fallback table when offline
+    pub fn release(&self, version: usize, target: &JavaTarget) -> Result<JavaRelease, FetchError> {
+        let key = Self::key(version, target);
+        if let Some(release) = self.resolved.lock().unwrap().get(&key) {
+            return Ok(release.clone());
+        }
+        let release = match self.latest(version, target) {
+            Ok(release) => release,
+            Err(e) => {
+                warn!("RESOLVING JAVA {} FAILED, USING FALLBACK: {}", version, e);
+                self.fallback
+                    .get(&key)
+                    .cloned()
+                    .ok_or(FetchError::UrlNotFound(version.to_string()))?
+            }
+        };
+        self.resolved.lock().unwrap().insert(key, release.clone());
+        Ok(release)
+    }
+    /// Whether the api has a build of the major version for the target, the build is kept
+    /// for `release`
+    fn available(&self, version: usize, target: &JavaTarget) -> bool {
+        let key = Self::key(version, target);
+        if self.resolved.lock().unwrap().contains_key(&key) {
+            return true;
+        }
+        match self.latest(version, target) {
+            Ok(release) => {
+                self.resolved.lock().unwrap().insert(key, release);
+                true
+            }
+            Err(e) => {
+                debug!("NO TEMURIN {} FOR {}: {}", version, key, e);
+                false
+            }
+        }
+    }
+}
+impl JavaProvider for Temurin {
+    fn name(&self) -> &str {
+        "temurin"
+    }
+    fn versions(&self, target: &JavaTarget) -> Result<Vec<usize>, FetchError> {
+        let url = format!("{}/v3/info/available_releases", self.base_url);
+        match http::get(&url, None).and_then(|r| r.success_json::<AdoptiumReleases>()) {
+            // the list covers every platform, each version is checked for the target
+            Ok(releases) => Ok(releases
+                .available_releases
+                .into_iter()
+                .filter(|version| self.available(*version, target))
+                .collect()),
+            Err(e) => {
+                warn!("LISTING TEMURIN RELEASES FAILED, USING FALLBACK: {}", e);
+                let prefix = format!("{}-{}-", target.os.name(), target.arch);
+                let mut versions: Vec<usize> = self
+                    .fallback
+                    .keys()
+                    .filter_map(|key| key.strip_prefix(&prefix)?.parse().ok())
+                    .collect();
+                versions.sort();
+                Ok(versions)
+            }
+        }
+    }
+    fn fetch(
+        &self,
+        version: usize,
+        target: &JavaTarget,
+        path: &str,
+    ) -> Result<Vec<DLFile>, FetchError> {
+        Ok(vec![self.release(version, target)?.file(path)])
+    }
+    fn directory(&self, version: usize, target: &JavaTarget) -> Option<String> {
+        self.release(version, target).ok()?.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{http::testing::serve, java::JavaUtil};
+
+    fn linux() -> JavaTarget {
+        JavaTarget::new(OperatingSystem::Linux, "x86_64")
+    }
+
+    #[test]
+    fn latest_release_from_the_api() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let base = serve(move |request| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            assert_eq!(
+                request.path,
+                "/v3/assets/latest/21/hotspot?architecture=x64&image_type=jre&os=linux&vendor=eclipse"
+            );
+            let body = json!([{
+                "release_name": "jdk-21.0.8+9",
+                "binary": {
+                    "image_type": "jre",
+                    "package": {
+                        "checksum": "abc",
+                        "link": "https://example.com/jre.tar.gz",
+                        "size": 42,
+                    },
+                },
+            }]);
+            (200, body.to_string())
+        });
+        let temurin = Temurin::new().with_base_url(&format!("{}/", base));
+        let release = temurin.release(21, &linux()).unwrap();
+        assert_eq!(release.id.as_deref(), Some("jdk-21.0.8+9-jre"));
+        assert_eq!(release.url, "https://example.com/jre.tar.gz");
+        assert_eq!(release.sha256, "abc");
+        assert_eq!(release.size, Some(42));
+        // resolved once per version and target
+        temurin.release(21, &linux()).unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn fallback_when_the_api_fails() {
+        let base = serve(|_| (503, String::new()));
+        let temurin = Temurin::new().with_base_url(&base);
+        let release = temurin.release(17, &linux()).unwrap();
+        assert_eq!(release.id.as_deref(), Some("jdk-17.0.9+9-jre"));
+        assert_eq!(temurin.versions(&linux()).unwrap(), [8, 17, 21]);
+        assert!(matches!(
+            temurin.release(11, &linux()),
+            Err(FetchError::UrlNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn java_util_lists_the_releases_of_the_target() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let base = serve(move |request| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            if request.path == "/v3/info/available_releases" {
+                return (
+                    200,
+                    json!({ "available_releases": [8, 11, 17, 21, 25] }).to_string(),
+                );
+            }
+            let version = request
+                .path
+                .strip_prefix("/v3/assets/latest/")
+                .and_then(|path| path.split('/').next())
+                .unwrap();
+            if version == "11" || version == "25" {
+                return (200, json!([]).to_string());
+            }
+            let body = json!([{
+                "release_name": format!("jdk-{}", version),
+                "binary": {
+                    "image_type": "jre",
+                    "package": {
+                        "checksum": "abc",
+                        "link": "https://example.com/jre.tar.gz",
+                        "size": 42,
+                    },
+                },
+            }]);
+            (200, body.to_string())
+        });
+        let java = JavaUtil::new()
+            .with_target(linux())
+            .with_adoptium_url(&base);
+        assert_eq!(java.versions().unwrap(), [8, 17, 21]);
+        assert_eq!(requests.load(Ordering::SeqCst), 6);
+        // the builds found while listing are reused
+        assert_eq!(java.id_of(17).as_deref(), Some("jdk-17-jre"));
+        assert_eq!(requests.load(Ordering::SeqCst), 6);
+    }
+}
@@ -0,0 +1,93 @@
+use dwldutil::DLFile;
+use tracing::debug;
+
+use crate::{
+    errors::FetchError,
+    http,
+    java::{JavaProvider, JavaRelease, JavaTarget},
+    os::system::OperatingSystem,
+};
+
+pub const CORRETTO_DOWNLOADS: &str = "https://corretto.aws/downloads";
+
+/// Amazon Corretto builds from the permanent `latest` links, only JDKs are published
+pub struct Corretto {
+    pub base_url: String,
+    /// Corretto has no listing api, these are the supported major versions
+    pub versions: Vec<usize>,
+}
+impl Default for Corretto {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Corretto {
+    pub fn new() -> Self {
+        Corretto {
+            base_url: CORRETTO_DOWNLOADS.to_owned(),
+            versions: vec![8, 11, 17, 21, 25],
+        }
+    }
+    pub fn with_base_url(mut self, url: &str) -> Self {
+        self.base_url = url.trim_end_matches('/').to_owned();
+        self
+    }
+    fn archive(version: usize, target: &JavaTarget) -> Result<String, FetchError> {
+        let os = match target.os {
+            OperatingSystem::Linux => "linux",
+            OperatingSystem::Windows => "windows",
+            OperatingSystem::MacOS => "macos",
+            OperatingSystem::Other => return Err(FetchError::OsUnsupported()),
+        };
+        let arch = match target.arch.as_str() {
+            "x86_64" => "x64",
+            "x86" => "x86",
+            "aarch64" => "aarch64",
+            _ => return Err(FetchError::OsUnsupported()),
+        };
+        Ok(format!(
+            "amazon-corretto-{}-{}-{}-jdk.{}",
+            version,
+            arch,
+            os,
+            target.archive_extension()
+        ))
+    }
+    pub fn release(&self, version: usize, target: &JavaTarget) -> Result<JavaRelease, FetchError> {
+        if !self.versions.contains(&version) {
+            return Err(FetchError::UrlNotFound(version.to_string()));
+        }
+        let archive = Self::archive(version, target)?;
+        let response = http::get(
+            &format!("{}/latest_sha256/{}", self.base_url, archive),
+            None,
+        )?;
+        if !response.is_success() {
+            return Err(FetchError::UrlNotFound(version.to_string()));
+        }
+        debug!("CORRETTO {} FOR JAVA {}", archive, version);
+        Ok(JavaRelease {
+            version,
+            id: None,
+            url: format!("{}/latest/{}", self.base_url, archive),
+            sha256: response.body.trim().to_owned(),
+            size: None,
+        })
+    }
+}
+impl JavaProvider for Corretto {
+    fn name(&self) -> &str {
+        "corretto"
+    }
+    fn versions(&self, _target: &JavaTarget) -> Result<Vec<usize>, FetchError> {
+        Ok(self.versions.clone())
+    }
+    fn fetch(
+        &self,
+        version: usize,
+        target: &JavaTarget,
+        path: &str,
+    ) -> Result<Vec<DLFile>, FetchError> {
+        Ok(vec![self.release(version, target)?.file(path)])
+    }
+}
@@ -0,0 +1,440 @@
+use std::{collections::BTreeMap, fs, io, path::Path, sync::Arc};
+
+use dwldutil::{
+    DLFile, DLHashes,
+    decompress::{DLDecompressionConfig, DecompressionMethod},
+};
+use tracing::{debug, error, warn};
+
+use crate::{
+    errors::{FetchError, JavaError},
+    java::{
+        adoptium::Temurin,
+        corretto::Corretto,
+        mojang::MojangRuntimes,
+        system::{InstalledJava, discover, find_compatible},
+        zulu::Zulu,
+    },
+    os::system::OperatingSystem,
+};
+
+pub mod adoptium;
+pub mod corretto;
+pub mod mojang;
+pub mod system;
+pub mod zulu;
+
+/// Platform a Java build is downloaded for
+#[derive(Debug, Clone)]
+pub struct JavaTarget {
+    pub os: OperatingSystem,
+    /// Architecture as in `std::env::consts::ARCH`
+    pub arch: String,
+}
+impl JavaTarget {
+    pub fn new(os: OperatingSystem, arch: &str) -> Self {
+        JavaTarget {
+            os,
+            arch: arch.to_owned(),
+        }
+    }
+    pub fn detect() -> Self {
+        Self::new(OperatingSystem::detect(), std::env::consts::ARCH)
+    }
+    /// Extension of the archives for the os
+    pub fn archive_extension(&self) -> &'static str {
+        match self.os {
+            OperatingSystem::Windows => "zip",
+            _ => "tar.gz",
+        }
+    }
+}
+
+/// Archive of a Java build
+#[derive(Debug, Clone)]
+pub struct JavaRelease {
+    pub version: usize,
+    /// Top level directory of the archive, when the vendor publishes it
+    pub id: Option<String>,
+    pub url: String,
+    pub sha256: String,
+    pub size: Option<u64>,
+}
+impl JavaRelease {
+    pub fn decompression_method(&self) -> DecompressionMethod {
+        if self.url.ends_with(".zip") {
+            DecompressionMethod::Zip
+        } else {
+            DecompressionMethod::TarGzip
+        }
+    }
+    /// Download of the archive, extracted into `path` and recorded in its install marker
+    pub fn file(&self, path: &str) -> DLFile {
+        let config = DLDecompressionConfig::new(self.decompression_method(), path);
+        let mut file = DLFile::new()
+            .with_url(&self.url)
+            .with_path(&format!("{}.tmp", path))
+            .with_hashes(DLHashes::new().sha256(&self.sha256))
+            .with_on_download(Arc::new(move |archive| {
+                if let Err(e) = extract(&archive, &config) {
+                    error!("EXTRACTING {} FAILED: {}", archive, e);
+                }
+            }));
+        if let Some(size) = self.size {
+            file = file.with_size(size);
+        }
+        file
+    }
+}
+
+/// Extracts the archive, the files are only recorded once all of them are there
+fn extract(archive: &str, config: &DLDecompressionConfig) -> Result<(), String> {
+    config.decompress(archive)?;
+    fs::remove_file(archive).map_err(|e| e.to_string())?;
+    let root = Path::new(&config.output);
+    let files = scan(root).map_err(|e| e.to_string())?;
+    InstallMarker::record(root, files).map_err(|e| e.to_string())
+}
+
+/// Files under the directory with their size, links are skipped
+fn scan(root: &Path) -> Result<BTreeMap<String, u64>, io::Error> {
+    let mut files = BTreeMap::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(entry.path());
+            } else if file_type.is_file() && entry.file_name() != INSTALL_MARKER {
+                let path = entry.path();
+                let name = path.strip_prefix(root).unwrap_or(&path).to_string_lossy();
+                files.insert(name.replace('\\', "/"), entry.metadata()?.len());
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Vendor of Java builds
+pub trait JavaProvider: Send + Sync {
+    /// Name used by `JavaUtil::set_distribution`
+    fn name(&self) -> &str;
+    /// Major versions offered for the target
+    fn versions(&self, target: &JavaTarget) -> Result<Vec<usize>, FetchError>;
+    /// Files of the build, placed or extracted under `path`
+    fn fetch(
+        &self,
+        version: usize,
+        target: &JavaTarget,
+        path: &str,
+    ) -> Result<Vec<DLFile>, FetchError>;
+    /// Directory of the build inside `path`, when it's known before downloading
+    fn directory(&self, _version: usize, _target: &JavaTarget) -> Option<String> {
+        None
+    }
+    /// Files of a runtime the vendor names instead of a major version, placed under `path`
+    fn fetch_runtime(
+        &self,
+        name: &str,
+        _target: &JavaTarget,
+        _path: &str,
+    ) -> Result<Vec<DLFile>, FetchError> {
+        Err(FetchError::UrlNotFound(name.to_owned()))
+    }
+}
+
+/// File `JavaUtil::prepare` leaves in the directories it installs into, see `InstallMarker`
+pub const INSTALL_MARKER: &str = ".mcd-java";
+
+/// Content of the `INSTALL_MARKER`: the version `prepare` installed and the files of the
+/// runtime with their size, relative to the install directory. The files are recorded by
+/// the providers, before downloading when they are known and after extracting archives
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InstallMarker {
+    pub version: Option<usize>,
+    pub files: BTreeMap<String, u64>,
+}
+impl InstallMarker {
+    /// Marker of the install directory, none when there is none
+    pub fn read(path: &Path) -> Option<Self> {
+        let content = fs::read_to_string(path.join(INSTALL_MARKER)).ok()?;
+        let mut marker = InstallMarker::default();
+        for line in content.lines() {
+            if let Some(version) = line.strip_prefix("version ") {
+                marker.version = version.trim().parse().ok();
+            } else if let Some(file) = line.strip_prefix("file ")
+                && let Some((size, name)) = file.split_once(' ')
+                && let Ok(size) = size.parse()
+            {
+                marker.files.insert(name.to_owned(), size);
+            }
+        }
+        Some(marker)
+    }
+    pub fn write(&self, path: &Path) -> Result<(), io::Error> {
+        let mut content = String::new();
+        if let Some(version) = self.version {
+            content.push_str(&format!("version {}\n", version));
+        }
+        for (name, size) in &self.files {
+            content.push_str(&format!("file {} {}\n", size, name));
+        }
+        fs::create_dir_all(path)?;
+        fs::write(path.join(INSTALL_MARKER), content)
+    }
+    /// Records the version `prepare` installs, keeping the files
+    pub fn mark(path: &Path, version: usize) -> Result<(), io::Error> {
+        let mut marker = Self::read(path).unwrap_or_default();
+        marker.version = Some(version);
+        marker.write(path)
+    }
+    /// Records the files of the install, keeping the version
+    pub fn record(path: &Path, files: BTreeMap<String, u64>) -> Result<(), io::Error> {
+        let mut marker = Self::read(path).unwrap_or_default();
+        marker.files = files;
+        marker.write(path)
+    }
+    /// Checks every recorded file is present with its size, an install without recorded
+    /// files never finished
+    pub fn check(&self, path: &Path) -> Result<(), JavaError> {
+        if self.files.is_empty() {
+            return Err(JavaError::MissingFile(
+                path.join(INSTALL_MARKER).display().to_string(),
+            ));
+        }
+        for (name, size) in &self.files {
+            let file = path.join(name);
+            if fs::metadata(&file).map(|metadata| metadata.len()).ok() != Some(*size) {
+                return Err(JavaError::MissingFile(file.display().to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Version `JavaUtil::prepare` installed into the directory, none when it didn't create it
+fn owner_of(path: &str) -> Option<usize> {
+    InstallMarker::read(Path::new(path))?.version
+}
+
+/// Where the Java for a launch comes from
+pub enum JavaSource {
+    /// JVM already on the system
+    Installed(InstalledJava),
+    /// Files to download into the path
+    Download(Vec<DLFile>),
+}
+
+pub struct JavaUtil {
+    pub providers: Vec<Box<dyn JavaProvider>>,
+    pub target: JavaTarget,
+    /// Whether `prepare` looks for a compatible JVM on the system first
+    pub use_system: bool,
+    distribution: String,
+}
+impl Default for JavaUtil {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl JavaUtil {
+    pub fn new() -> Self {
+        JavaUtil {
+            providers: vec![
+                Box::new(Temurin::new()),
+                Box::new(Zulu::new()),
+                Box::new(Corretto::new()),
+                Box::new(MojangRuntimes::new()),
+            ],
+            target: JavaTarget::detect(),
+            use_system: true,
+            distribution: String::from("temurin"),
+        }
+    }
+    /// Provider used by `fetch`, by its name: temurin, zulu, corretto or mojang
+    pub fn set_distribution(&mut self, distribution: &str) {
+        self.distribution = distribution.to_owned();
+    }
+    /// Adds the provider, replacing the one with the same name
+    pub fn with_provider(mut self, provider: Box<dyn JavaProvider>) -> Self {
+        self.providers.retain(|p| p.name() != provider.name());
+        self.providers.push(provider);
+        self
+    }
+    pub fn with_target(mut self, target: JavaTarget) -> Self {
+        self.target = target;
+        self
+    }
+    pub fn with_system_java(mut self, use_system: bool) -> Self {
+        self.use_system = use_system;
+        self
+    }
+    pub fn with_runtime_index(self, url: &str) -> Self {
+        self.with_provider(Box::new(MojangRuntimes::new().with_index_url(url)))
+    }
+    pub fn with_adoptium_url(self, url: &str) -> Self {
+        self.with_provider(Box::new(Temurin::new().with_base_url(url)))
+    }
+    pub fn provider(&self) -> Result<&dyn JavaProvider, FetchError> {
+        // former name of Temurin
+        let name = match self.distribution.as_str() {
+            "adopt" => "temurin",
+            name => name,
+        };
+        self.provider_named(name)
+    }
+    fn provider_named(&self, name: &str) -> Result<&dyn JavaProvider, FetchError> {
+        self.providers
+            .iter()
+            .find(|provider| provider.name() == name)
+            .map(|provider| provider.as_ref())
+            .ok_or(FetchError::UnknownDistribution(name.to_owned()))
+    }
+    /// Major versions the distribution offers for the target
+    pub fn versions(&self) -> Result<Vec<usize>, FetchError> {
+        self.provider()?.versions(&self.target)
+    }
+    /// Files of the Mojang runtime named by `javaVersion.component`, e.g. `java-runtime-gamma`
+    pub fn fetch_runtime(&self, component: &str, path: &str) -> Result<Vec<DLFile>, FetchError> {
+        self.provider_named("mojang")?
+            .fetch_runtime(component, &self.target, path)
+    }
+    pub fn fetch(&self, version: usize, path: &str) -> Result<Vec<DLFile>, FetchError> {
+        if Path::new(path).exists() {
+            return Err(FetchError::PathAlredyExist(path.to_owned()));
+        }
+        let provider = self.provider()?;
+        debug!("JAVA {} FROM {}", version, provider.name());
+        provider.fetch(version, &self.target, path)
+    }
+    /// Compatible JVM installed on the system, see `InstalledJava::is_compatible`
+    pub fn locate(&self, version: usize) -> Option<InstalledJava> {
+        find_compatible(&discover(), version).cloned()
+    }
+    /// Runtime previously installed into `path`, checked to be of the version and to have
+    /// every file of its install marker
+    pub fn installed(&self, version: usize, path: &str) -> Result<InstalledJava, JavaError> {
+        if let Some(marker) = InstallMarker::read(Path::new(path)) {
+            marker.check(Path::new(path))?;
+        }
+        let java = InstalledJava::load(Path::new(path))?;
+        java.validate(version)?;
+        Ok(java)
+    }
+    /// Installed JVM for the version: from the system when allowed, then the one in `path`,
+    /// otherwise the files to download. A broken install is only removed when `prepare`
+    /// created it for the same version, any other content of `path` is reported as an error
+    pub fn prepare(&self, version: usize, path: &str) -> Result<JavaSource, JavaError> {
+        if self.use_system
+            && let Some(java) = self.locate(version)
+        {
+            debug!(
+                "USING SYSTEM JAVA {} AT {}",
+                java.version,
+                java.home.display()
+            );
+            return Ok(JavaSource::Installed(java));
+        }
+        match self.installed(version, path) {
+            Ok(java) => return Ok(JavaSource::Installed(java)),
+            Err(JavaError::NotInstalled(_)) if !Path::new(path).exists() => {}
+            Err(e) if owner_of(path) == Some(version) => {
+                warn!("REINSTALLING JAVA AT {}: {}", path, e);
+                fs::remove_dir_all(path)?;
+            }
+            Err(e) => return Err(e),
+        }
+        let archive = format!("{}.tmp", path);
+        if Path::new(&archive).exists() {
+            fs::remove_file(&archive)?;
+        }
+        let files = self.fetch(version, path)?;
+        InstallMarker::mark(Path::new(path), version)?;
+        Ok(JavaSource::Download(files))
+    }
+    pub fn id_of(&self, version: usize) -> Option<String> {
+        self.provider().ok()?.directory(version, &self.target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn install(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("mcd-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("jdk/bin")).unwrap();
+        fs::write(dir.join("jdk/bin/java"), "java").unwrap();
+        fs::write(dir.join("jdk/release"), "JAVA_VERSION=\"21\"").unwrap();
+        dir
+    }
+
+    #[test]
+    fn marker_keeps_version_and_files() {
+        let dir = install("marker");
+        InstallMarker::mark(&dir, 21).unwrap();
+        InstallMarker::record(&dir, scan(&dir).unwrap()).unwrap();
+        let marker = InstallMarker::read(&dir).unwrap();
+        assert_eq!(marker.version, Some(21));
+        assert_eq!(
+            marker.files,
+            BTreeMap::from([
+                (String::from("jdk/bin/java"), 4),
+                (String::from("jdk/release"), 17)
+            ])
+        );
+        assert_eq!(owner_of(&dir.to_string_lossy()), Some(21));
+        marker.check(&dir).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn marker_reports_missing_and_truncated_files() {
+        let dir = install("missing");
+        InstallMarker::record(&dir, scan(&dir).unwrap()).unwrap();
+        let marker = InstallMarker::read(&dir).unwrap();
+        fs::write(dir.join("jdk/release"), "").unwrap();
+        assert!(
+            matches!(marker.check(&dir), Err(JavaError::MissingFile(file)) if file.ends_with("release"))
+        );
+        fs::remove_file(dir.join("jdk/bin/java")).unwrap();
+        assert!(
+            matches!(marker.check(&dir), Err(JavaError::MissingFile(file)) if file.ends_with("java"))
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unfinished_install_is_incomplete() {
+        let dir = install("unfinished");
+        InstallMarker::mark(&dir, 21).unwrap();
+        let result = JavaUtil::new().installed(21, &dir.to_string_lossy());
+        assert!(matches!(result, Err(JavaError::MissingFile(_))));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn runtimes_come_from_the_registered_provider() {
+        let base = crate::http::testing::serve(|request| {
+            assert_eq!(request.path, "/runtimes.json");
+            (200, String::from("{}"))
+        });
+        let java = JavaUtil::new()
+            .with_target(JavaTarget::new(OperatingSystem::Linux, "x86_64"))
+            .with_runtime_index(&format!("{}/runtimes.json", base));
+        let result = java.fetch_runtime("java-runtime-gamma", "unused");
+        assert!(
+            matches!(result, Err(FetchError::UrlNotFound(name)) if name == "java-runtime-gamma")
+        );
+        let without = JavaUtil {
+            providers: Vec::new(),
+            ..JavaUtil::new()
+        };
+        assert!(matches!(
+            without.fetch_runtime("java-runtime-gamma", "unused"),
+            Err(FetchError::UnknownDistribution(_))
+        ));
+    }
+}
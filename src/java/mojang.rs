@@ -0,0 +1,393 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::{self, File},
+    io::BufReader,
+    path::Path,
+    sync::Arc,
+};
+
+use dwldutil::{DLFile, DLHashType, DLHashes};
+use serde::Deserialize;
+use tracing::{debug, error};
+
+use crate::{
+    errors::FetchError,
+    http,
+    java::{InstallMarker, JavaProvider, JavaTarget},
+    os::system::OperatingSystem,
+};
+
+pub const JAVA_RUNTIME_INDEX: &str = "https://launchermeta.mojang.com/v1/products/java-runtime/2ec0cc96c44e5a76b9c8b7c39df7210883d12871/all.json";
+
+/// Runtimes by platform, then by component
+pub type RuntimeIndex = HashMap<String, HashMap<String, Vec<RuntimeEntry>>>;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RuntimeEntry {
+    pub manifest: RuntimeDownload,
+    pub version: RuntimeVersion,
+}
+#[derive(Deserialize, Debug, Clone)]
+pub struct RuntimeDownload {
+    pub sha1: String,
+    pub size: u64,
+    pub url: String,
+}
+#[derive(Deserialize, Debug, Clone)]
+pub struct RuntimeVersion {
+    pub name: String,
+    pub released: String,
+}
+#[derive(Deserialize, Debug, Clone)]
+pub struct RuntimeManifest {
+    pub files: HashMap<String, RuntimeFile>,
+}
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum RuntimeFile {
+    File {
+        #[serde(default)]
+        executable: bool,
+        downloads: RuntimeFileDownloads,
+    },
+    Directory,
+    Link {
+        target: String,
+    },
+}
+#[derive(Deserialize, Debug, Clone)]
+pub struct RuntimeFileDownloads {
+    pub raw: RuntimeDownload,
+    pub lzma: Option<RuntimeDownload>,
+}
+
+/// Name of the target in the runtime index
+pub fn platform(target: &JavaTarget) -> Option<&'static str> {
+    match (target.os, target.arch.as_str()) {
+        (OperatingSystem::Linux, "x86_64") => Some("linux"),
+        (OperatingSystem::Linux, "x86") => Some("linux-i386"),
+        (OperatingSystem::Windows, "x86_64") => Some("windows-x64"),
+        (OperatingSystem::Windows, "x86") => Some("windows-x86"),
+        (OperatingSystem::Windows, "aarch64") => Some("windows-arm64"),
+        (OperatingSystem::MacOS, "x86_64") => Some("mac-os"),
+        (OperatingSystem::MacOS, "aarch64") => Some("mac-os-arm64"),
+        _ => None,
+    }
+}
+
+/// Java runtimes the official launcher installs, named by `javaVersion.component`
+pub struct MojangRuntimes {
+    pub index_url: String,
+}
+impl Default for MojangRuntimes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl MojangRuntimes {
+    pub fn new() -> Self {
+        MojangRuntimes {
+            index_url: JAVA_RUNTIME_INDEX.to_owned(),
+        }
+    }
+    pub fn with_index_url(mut self, url: &str) -> Self {
+        self.index_url = url.to_owned();
+        self
+    }
+    pub fn index(&self) -> Result<RuntimeIndex, FetchError> {
+        Ok(http::get(&self.index_url, None)?.success_json()?)
+    }
+    /// Runtimes of the target by component
+    pub fn components(
+        &self,
+        target: &JavaTarget,
+    ) -> Result<HashMap<String, Vec<RuntimeEntry>>, FetchError> {
+        let platform = platform(target).ok_or(FetchError::OsUnsupported())?;
+        Ok(self.index()?.remove(platform).unwrap_or_default())
+    }
+    /// Runtime of the component for the target
+    pub fn resolve(
+        &self,
+        component: &str,
+        target: &JavaTarget,
+    ) -> Result<RuntimeEntry, FetchError> {
+        let platform = platform(target).ok_or(FetchError::OsUnsupported())?;
+        debug!("RESOLVING RUNTIME {} FOR {}", component, platform);
+        self.index()?
+            .remove(platform)
+            .and_then(|mut components| components.remove(component))
+            .and_then(|entries| entries.into_iter().next())
+            .ok_or(FetchError::UrlNotFound(component.to_owned()))
+    }
+    pub fn manifest(&self, entry: &RuntimeEntry) -> Result<RuntimeManifest, FetchError> {
+        let response = http::get(&entry.manifest.url, None)?;
+        if response.is_success()
+            && !DLHashType::SHA1.verify_data(response.body.as_bytes(), &entry.manifest.sha1)
+        {
+            return Err(FetchError::HashMismatch(entry.manifest.url.clone()));
+        }
+        Ok(response.success_json()?)
+    }
+    /// Files of the component to download into `path`, directories and links are created here
+    /// and every file is recorded in the install marker
+    pub fn fetch_component(
+        &self,
+        component: &str,
+        target: &JavaTarget,
+        path: &str,
+    ) -> Result<Vec<DLFile>, FetchError> {
+        let entry = self.resolve(component, target)?;
+        debug!("RUNTIME {} VERSION {}", component, entry.version.name);
+        let manifest = self.manifest(&entry)?;
+        let root = Path::new(path);
+
+        // links point to files of the runtime, they are copied where symlinks aren't available
+        let mut copies: HashMap<String, Vec<String>> = HashMap::new();
+        for (name, file) in &manifest.files {
+            match file {
+                RuntimeFile::Directory => fs::create_dir_all(root.join(name))?,
+                RuntimeFile::Link { target } => {
+                    let link = root.join(name);
+                    if let Some(parent) = link.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    if fs::symlink_metadata(&link).is_ok() {
+                        continue;
+                    }
+                    #[cfg(unix)]
+                    std::os::unix::fs::symlink(target, &link)?;
+                    #[cfg(not(unix))]
+                    {
+                        let parent = Path::new(name).parent().unwrap_or(Path::new(""));
+                        let target = normalize(&parent.join(target));
+                        copies
+                            .entry(target)
+                            .or_default()
+                            .push(link.to_string_lossy().into_owned());
+                    }
+                }
+                RuntimeFile::File { .. } => {}
+            }
+        }
+
+        let listed = manifest
+            .files
+            .iter()
+            .filter_map(|(name, file)| match file {
+                RuntimeFile::File { downloads, .. } => Some((name.clone(), downloads.raw.size)),
+                _ => None,
+            })
+            .collect();
+        InstallMarker::record(root, listed)?;
+
+        let mut files = Vec::new();
+        for (name, file) in manifest.files {
+            let RuntimeFile::File {
+                executable,
+                downloads,
+            } = file
+            else {
+                continue;
+            };
+            let dest = root.join(&name).to_string_lossy().into_owned();
+            let links = copies.remove(&name).unwrap_or_default();
+            // checked on every run, a file that failed to unpack is fetched again
+            if Path::new(&dest).is_file()
+                && DLHashType::SHA1.verify_file(Path::new(&dest), &downloads.raw.sha1)
+            {
+                let _ = fs::remove_file(format!("{}.lzma", dest));
+                finish(&dest, executable, &links);
+                continue;
+            }
+            files.push(runtime_file(dest, executable, downloads, links));
+        }
+        debug!("RUNTIME {} NEEDS {} FILES", component, files.len());
+        Ok(files)
+    }
+}
+
+impl JavaProvider for MojangRuntimes {
+    fn name(&self) -> &str {
+        "mojang"
+    }
+    fn versions(&self, target: &JavaTarget) -> Result<Vec<usize>, FetchError> {
+        let versions: BTreeSet<usize> = self
+            .components(target)?
+            .values()
+            .flatten()
+            .filter_map(|entry| major(&entry.version.name))
+            .collect();
+        Ok(versions.into_iter().collect())
+    }
+    /// Files of the most recent component with the major version, written directly under `path`
+    fn fetch(
+        &self,
+        version: usize,
+        target: &JavaTarget,
+        path: &str,
+    ) -> Result<Vec<DLFile>, FetchError> {
+        let component = self
+            .components(target)?
+            .into_iter()
+            .filter_map(|(component, entries)| Some((component, entries.into_iter().next()?)))
+            .filter(|(_, entry)| major(&entry.version.name) == Some(version))
+            .max_by(|(_, a), (_, b)| a.version.released.cmp(&b.version.released))
+            .map(|(component, _)| component)
+            .ok_or(FetchError::UrlNotFound(version.to_string()))?;
+        self.fetch_component(&component, target, path)
+    }
+    fn directory(&self, _version: usize, _target: &JavaTarget) -> Option<String> {
+        Some(String::new())
+    }
+    fn fetch_runtime(
+        &self,
+        name: &str,
+        target: &JavaTarget,
+        path: &str,
+    ) -> Result<Vec<DLFile>, FetchError> {
+        self.fetch_component(name, target, path)
+    }
+}
+
+/// Major version of a runtime version name, `8u51`, `1.8.0_51` or `17.0.8`
+fn major(name: &str) -> Option<usize> {
+    let name = name.strip_prefix("1.").unwrap_or(name);
+    let digits: String = name.chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+/// Download of a runtime file, the lzma variant is preferred and unpacked once downloaded
+fn runtime_file(
+    dest: String,
+    executable: bool,
+    downloads: RuntimeFileDownloads,
+    links: Vec<String>,
+) -> DLFile {
+    let raw = downloads.raw;
+    match downloads.lzma {
+        Some(lzma) => {
+            let dest_clone = dest.clone();
+            DLFile::new()
+                .with_url(&lzma.url)
+                .with_path(&format!("{}.lzma", dest))
+                .with_size(lzma.size)
+                .with_hashes(DLHashes::new().sha1(&lzma.sha1))
+                .with_on_download(Arc::new(move |compressed| {
+                    if let Err(e) = unpack(&compressed, &dest_clone, &raw.sha1) {
+                        error!("UNPACKING {} FAILED: {}", dest_clone, e);
+                        return;
+                    }
+                    finish(&dest_clone, executable, &links);
+                }))
+        }
+        None => DLFile::new()
+            .with_url(&raw.url)
+            .with_path(&dest)
+            .with_size(raw.size)
+            .with_hashes(DLHashes::new().sha1(&raw.sha1))
+            .with_on_download(Arc::new(move |dest| finish(&dest, executable, &links))),
+    }
+}
+
+/// Unpacks next to `dest` and only replaces it once the hash matches. On failure the
+/// compressed file is kept, the install reports it as left over until it's fetched again
+fn unpack(compressed: &str, dest: &str, sha1: &str) -> Result<(), String> {
+    let unpacked = format!("{}.unpacked", dest);
+    let result = (|| {
+        let mut input = BufReader::new(File::open(compressed).map_err(|e| e.to_string())?);
+        let mut output = File::create(&unpacked).map_err(|e| e.to_string())?;
+        lzma_rs::lzma_decompress(&mut input, &mut output).map_err(|e| e.to_string())?;
+        if !DLHashType::SHA1.verify_file(Path::new(&unpacked), sha1) {
+            return Err(String::from("hash verification failed"));
+        }
+        fs::rename(&unpacked, dest).map_err(|e| e.to_string())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&unpacked);
+        return result;
+    }
+    fs::remove_file(compressed).map_err(|e| e.to_string())
+}
+
+/// Restores the executable bit and copies the file to the links that point to it
+fn finish(dest: &str, executable: bool, links: &[String]) {
+    #[cfg(unix)]
+    if executable {
+        use std::os::unix::fs::PermissionsExt;
+        if let Err(e) = fs::set_permissions(dest, fs::Permissions::from_mode(0o755)) {
+            error!("SETTING EXECUTABLE BIT ON {} FAILED: {}", dest, e);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = executable;
+    for link in links {
+        if let Err(e) = fs::copy(dest, link) {
+            error!("COPYING {} TO {} FAILED: {}", dest, link, e);
+        }
+    }
+}
+
+/// Resolves `..` of a link target relative to the runtime root
+#[cfg(not(unix))]
+fn normalize(path: &Path) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for part in path.components() {
+        match part {
+            std::path::Component::ParentDir => {
+                parts.pop();
+            }
+            std::path::Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            _ => {}
+        }
+    }
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    const HELLO_SHA1: &str = "2aae6c35c94fcfb415dbe95f408b9ce91ee846ed";
+
+    fn compressed(dir: &Path) -> String {
+        fs::create_dir_all(dir).unwrap();
+        let mut data = Vec::new();
+        lzma_rs::lzma_compress(&mut Cursor::new(b"hello world"), &mut data).unwrap();
+        let path = dir.join("lib.so.lzma");
+        fs::write(&path, data).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn unpack_replaces_the_compressed_file() {
+        let dir = std::env::temp_dir().join(format!("mcd-unpack-{}", std::process::id()));
+        let dest = dir.join("lib.so").to_string_lossy().into_owned();
+        let lzma = compressed(&dir);
+        unpack(&lzma, &dest, HELLO_SHA1).unwrap();
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "hello world");
+        assert!(!Path::new(&lzma).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unpack_keeps_the_compressed_file_on_mismatch() {
+        let dir = std::env::temp_dir().join(format!("mcd-mismatch-{}", std::process::id()));
+        let dest = dir.join("lib.so").to_string_lossy().into_owned();
+        let lzma = compressed(&dir);
+        assert!(unpack(&lzma, &dest, "0000").is_err());
+        assert!(Path::new(&lzma).exists());
+        assert!(!Path::new(&dest).exists());
+        assert!(!Path::new(&format!("{}.unpacked", dest)).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn major_of_runtime_names() {
+        assert_eq!(major("8u51"), Some(8));
+        assert_eq!(major("1.8.0_51"), Some(8));
+        assert_eq!(major("17.0.8"), Some(17));
+        assert_eq!(major("beta"), None);
+    }
+}
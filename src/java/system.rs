@@ -0,0 +1,308 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use tracing::debug;
+
+use crate::{crash::parse_java_major, errors::JavaError, os::system::OperatingSystem};
+
+/// JVM found on the system
+#[derive(Debug, Clone)]
+pub struct InstalledJava {
+    pub home: PathBuf,
+    pub executable: PathBuf,
+    pub version: String,
+    pub major: usize,
+    pub vendor: Option<String>,
+    /// Architecture as in `std::env::consts::ARCH`
+    pub arch: Option<String>,
+}
+impl InstalledJava {
+    /// Runtime installed under `path` by `JavaUtil::fetch` or `JavaUtil::fetch_runtime`
+    pub fn load(path: &Path) -> Result<Self, JavaError> {
+        let not_installed = || JavaError::NotInstalled(path.display().to_string());
+        let home = find_home(path).ok_or_else(not_installed)?;
+        let executable = home.join("bin").join(executable_name());
+        check_executable(&executable)?;
+        // the release file can outlive a broken install, running java is the real check
+        let mut java = probe(&executable).ok_or_else(not_installed)?;
+        java.home = home;
+        Ok(java)
+    }
+    /// Checks the runtime has no leftovers and the required major version, 0 accepts any.
+    /// The files of installs with a marker are checked by `InstallMarker::check`
+    pub fn validate(&self, required: usize) -> Result<(), JavaError> {
+        check_executable(&self.executable)?;
+        if required != 0 && self.major != required {
+            return Err(JavaError::VersionMismatch(required, self.major));
+        }
+        if let Some(leftover) = leftover(&self.home) {
+            return Err(JavaError::Incomplete(leftover.display().to_string()));
+        }
+        Ok(())
+    }
+    /// Whether it can run a version that requires the Java major version
+    pub fn is_compatible(&self, required: usize) -> bool {
+        // Java 8 versions break on newer Java, from 16 (1.17) on newer releases run fine
+        let version_ok = self.major == required || (required >= 16 && self.major > required);
+        let arch_ok = self
+            .arch
+            .as_ref()
+            .is_none_or(|arch| arch == env::consts::ARCH);
+        version_ok && arch_ok
+    }
+}
+
+/// Name of the java executable for the os
+pub fn executable_name() -> &'static str {
+    match OperatingSystem::detect() {
+        OperatingSystem::Windows => "java.exe",
+        _ => "java",
+    }
+}
+
+/// Java home inside an install directory: the directory itself, the archive top level
+/// directory or the `Contents/Home` of a macOS bundle
+pub fn find_home(path: &Path) -> Option<PathBuf> {
+    let mut dirs = vec![path.to_path_buf()];
+    for _ in 0..4 {
+        let mut next = Vec::new();
+        for dir in dirs {
+            if dir.join("bin").join(executable_name()).is_file() {
+                return Some(dir);
+            }
+            if let Ok(entries) = fs::read_dir(&dir) {
+                next.extend(
+                    entries
+                        .flatten()
+                        .map(|entry| entry.path())
+                        .filter(|path| path.is_dir()),
+                );
+            }
+        }
+        dirs = next;
+    }
+    None
+}
+
+fn check_executable(executable: &Path) -> Result<(), JavaError> {
+    let metadata = fs::metadata(executable)
+        .map_err(|_| JavaError::NotInstalled(executable.display().to_string()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o111 == 0 {
+            return Err(JavaError::NotExecutable(executable.display().to_string()));
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = metadata;
+    Ok(())
+}
+
+/// File of an interrupted install: a packed or half unpacked runtime file or a link without
+/// target
+fn leftover(dir: &Path) -> Option<PathBuf> {
+    for entry in fs::read_dir(dir).ok()?.flatten() {
+        let path = entry.path();
+        let file_type = entry.file_type().ok()?;
+        if file_type.is_symlink() {
+            if fs::metadata(&path).is_err() {
+                return Some(path);
+            }
+        } else if file_type.is_dir() {
+            if let Some(leftover) = leftover(&path) {
+                return Some(leftover);
+            }
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext == "lzma" || ext == "unpacked")
+        {
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// `std::env::consts::ARCH` of an `os.arch` or `OS_ARCH` value
+fn normalize_arch(arch: &str) -> String {
+    match arch {
+        "amd64" | "x86_64" | "x64" => String::from("x86_64"),
+        "aarch64" | "arm64" => String::from("aarch64"),
+        "x86" | "i386" | "i586" | "i686" => String::from("x86"),
+        arch => arch.to_owned(),
+    }
+}
+
+/// Java homes in the usual locations, JAVA_HOME, SDKMAN and PATH
+pub fn candidates() -> Vec<PathBuf> {
+    let mut homes = Vec::new();
+    if let Some(home) = env::var_os("JAVA_HOME") {
+        homes.push(PathBuf::from(home));
+    }
+
+    let mut roots = vec![
+        PathBuf::from("/usr/lib/jvm"),
+        PathBuf::from("/usr/lib64/jvm"),
+        PathBuf::from("/usr/java"),
+        PathBuf::from("/opt/java"),
+        PathBuf::from("/Library/Java/JavaVirtualMachines"),
+    ];
+    match env::var_os("SDKMAN_DIR") {
+        Some(sdkman) => roots.push(PathBuf::from(sdkman).join("candidates/java")),
+        None => {
+            if let Some(home) = env::var_os("HOME") {
+                roots.push(PathBuf::from(home).join(".sdkman/candidates/java"));
+            }
+        }
+    }
+    for root in roots {
+        let Ok(entries) = fs::read_dir(&root) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let home = entry.path();
+            // macOS bundles keep the home inside the bundle
+            let bundle = home.join("Contents/Home");
+            homes.push(if bundle.is_dir() { bundle } else { home });
+        }
+    }
+
+    if let Some(path) = env::var_os("PATH") {
+        for dir in env::split_paths(&path) {
+            let executable = dir.join(executable_name());
+            // the PATH entry is usually a symlink chain into the real home
+            if let Ok(executable) = fs::canonicalize(executable)
+                && let Some(home) = executable.parent().and_then(|bin| bin.parent())
+            {
+                homes.push(home.to_path_buf());
+            }
+        }
+    }
+    homes
+}
+
+/// Every JVM found by `candidates`, without duplicates
+pub fn discover() -> Vec<InstalledJava> {
+    let mut seen = HashSet::new();
+    let mut found = Vec::new();
+    for home in candidates() {
+        let Ok(home) = fs::canonicalize(&home) else {
+            continue;
+        };
+        if !seen.insert(home.clone()) {
+            continue;
+        }
+        if let Some(java) = inspect(&home) {
+            debug!("FOUND JAVA {} AT {}", java.version, home.display());
+            found.push(java);
+        }
+    }
+    found
+}
+
+/// Reads the `release` file of the home, probing the executable when it's missing
+pub fn inspect(home: &Path) -> Option<InstalledJava> {
+    let executable = home.join("bin").join(executable_name());
+    if !executable.is_file() {
+        return None;
+    }
+    let Ok(release) = fs::read_to_string(home.join("release")) else {
+        return probe(&executable);
+    };
+    let values: HashMap<&str, &str> = release
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim().trim_matches('"')))
+        .collect();
+    let Some(version) = values.get("JAVA_VERSION") else {
+        return probe(&executable);
+    };
+    Some(InstalledJava {
+        home: home.to_path_buf(),
+        executable,
+        version: version.to_string(),
+        major: parse_java_major(version)?,
+        vendor: values.get("IMPLEMENTOR").map(|vendor| vendor.to_string()),
+        arch: values.get("OS_ARCH").map(|arch| normalize_arch(arch)),
+    })
+}
+
+/// Runs `java -XshowSettings:properties -version`, the properties are printed to stderr
+pub fn probe(executable: &Path) -> Option<InstalledJava> {
+    let output = Command::new(executable)
+        .args(["-XshowSettings:properties", "-version"])
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&output.stderr);
+    let properties: HashMap<&str, &str> = text
+        .lines()
+        .filter_map(|line| line.split_once(" = "))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .collect();
+    let version = properties.get("java.version")?;
+    let home = match properties.get("java.home") {
+        Some(home) => PathBuf::from(home),
+        None => executable.parent()?.parent()?.to_path_buf(),
+    };
+    Some(InstalledJava {
+        home,
+        executable: executable.to_path_buf(),
+        version: version.to_string(),
+        major: parse_java_major(version)?,
+        vendor: properties
+            .get("java.vendor")
+            .map(|vendor| vendor.to_string()),
+        arch: properties.get("os.arch").map(|arch| normalize_arch(arch)),
+    })
+}
+
+/// Best installed JVM for the required major version, the exact one before newer ones
+pub fn find_compatible(installed: &[InstalledJava], required: usize) -> Option<&InstalledJava> {
+    installed
+        .iter()
+        .filter(|java| java.is_compatible(required))
+        .min_by_key(|java| (java.major != required, java.major))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn java(major: usize) -> InstalledJava {
+        InstalledJava {
+            home: PathBuf::from(format!("/jvm/{}", major)),
+            executable: PathBuf::from(format!("/jvm/{}/bin/java", major)),
+            version: major.to_string(),
+            major,
+            vendor: None,
+            arch: None,
+        }
+    }
+
+    #[test]
+    fn newer_java_runs_from_16_on() {
+        assert!(java(8).is_compatible(8));
+        assert!(!java(17).is_compatible(8));
+        assert!(!java(8).is_compatible(16));
+        assert!(java(16).is_compatible(16));
+        assert!(java(17).is_compatible(16));
+        assert!(java(21).is_compatible(17));
+        assert!(!java(17).is_compatible(21));
+    }
+
+    #[test]
+    fn exact_major_is_preferred() {
+        let installed = [java(21), java(8), java(17), java(16)];
+        assert_eq!(find_compatible(&installed, 16).unwrap().major, 16);
+        assert_eq!(find_compatible(&installed[..3], 16).unwrap().major, 17);
+        assert_eq!(find_compatible(&installed, 8).unwrap().major, 8);
+        assert!(find_compatible(&installed[..1], 8).is_none());
+        let mut foreign = java(17);
+        foreign.arch = Some(String::from("not-an-arch"));
+        assert!(!foreign.is_compatible(17));
+    }
+}
@@ -0,0 +1,200 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Mutex,
+};
+
+use dwldutil::DLFile;
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::{
+    errors::FetchError,
+    http,
+    java::{JavaProvider, JavaRelease, JavaTarget},
+    os::system::OperatingSystem,
+};
+
+pub const ZULU_API: &str = "https://api.azul.com/metadata/v1/zulu/packages";
+
+#[derive(Deserialize)]
+struct ZuluPackage {
+    package_uuid: String,
+    name: String,
+    java_version: Vec<usize>,
+    download_url: String,
+}
+#[derive(Deserialize)]
+struct ZuluDetails {
+    sha256_hash: String,
+    size: u64,
+}
+
+/// Azul Zulu builds from the Azul metadata api
+pub struct Zulu {
+    pub base_url: String,
+    resolved: Mutex<HashMap<String, JavaRelease>>,
+}
+impl Default for Zulu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Zulu {
+    pub fn new() -> Self {
+        Zulu {
+            base_url: ZULU_API.to_owned(),
+            resolved: Mutex::new(HashMap::new()),
+        }
+    }
+    pub fn with_base_url(mut self, url: &str) -> Self {
+        self.base_url = url.trim_end_matches('/').to_owned();
+        self
+    }
+    /// Filters of the latest GA JRE packages for the target
+    fn query(target: &JavaTarget) -> Result<String, FetchError> {
+        let os = match target.os {
+            OperatingSystem::Linux => "linux",
+            OperatingSystem::Windows => "windows",
+            OperatingSystem::MacOS => "macos",
+            OperatingSystem::Other => return Err(FetchError::OsUnsupported()),
+        };
+        let arch = match target.arch.as_str() {
+            "x86_64" => "x64",
+            "x86" => "x86",
+            "aarch64" => "aarch64",
+            "arm" => "arm",
+            _ => return Err(FetchError::OsUnsupported()),
+        };
+        Ok(format!(
+            "os={}&arch={}&archive_type={}&java_package_type=jre&javafx_bundled=false&release_status=ga&availability_types=CA&latest=true",
+            os,
+            arch,
+            target.archive_extension()
+        ))
+    }
+    /// Latest package of the major version for the target, resolved once
+    pub fn release(&self, version: usize, target: &JavaTarget) -> Result<JavaRelease, FetchError> {
+        let key = format!("{}-{}-{}", target.os.name(), target.arch, version);
+        if let Some(release) = self.resolved.lock().unwrap().get(&key) {
+            return Ok(release.clone());
+        }
+        let release = self.latest(version, target)?;
+        self.resolved.lock().unwrap().insert(key, release.clone());
+        Ok(release)
+    }
+    fn latest(&self, version: usize, target: &JavaTarget) -> Result<JavaRelease, FetchError> {
+        let url = format!(
+            "{}/?java_version={}&{}&page=1&page_size=1",
+            self.base_url,
+            version,
+            Self::query(target)?
+        );
+        let package = http::get(&url, None)?
+            .success_json::<Vec<ZuluPackage>>()?
+            .into_iter()
+            .next()
+            .ok_or(FetchError::UrlNotFound(version.to_string()))?;
+        let details: ZuluDetails =
+            http::get(&format!("{}/{}", self.base_url, package.package_uuid), None)?
+                .success_json()?;
+        debug!("ZULU {} FOR JAVA {}", package.name, version);
+        let id = package
+            .name
+            .trim_end_matches(".zip")
+            .trim_end_matches(".tar.gz")
+            .to_owned();
+        Ok(JavaRelease {
+            version,
+            id: Some(id),
+            url: package.download_url,
+            sha256: details.sha256_hash,
+            size: Some(details.size),
+        })
+    }
+}
+impl JavaProvider for Zulu {
+    fn name(&self) -> &str {
+        "zulu"
+    }
+    fn versions(&self, target: &JavaTarget) -> Result<Vec<usize>, FetchError> {
+        let url = format!(
+            "{}/?{}&page=1&page_size=1000",
+            self.base_url,
+            Self::query(target)?
+        );
+        let packages: Vec<ZuluPackage> = http::get(&url, None)?.success_json()?;
+        let versions: BTreeSet<usize> = packages
+            .iter()
+            .filter_map(|package| package.java_version.first().copied())
+            .collect();
+        Ok(versions.into_iter().collect())
+    }
+    fn fetch(
+        &self,
+        version: usize,
+        target: &JavaTarget,
+        path: &str,
+    ) -> Result<Vec<DLFile>, FetchError> {
+        Ok(vec![self.release(version, target)?.file(path)])
+    }
+    fn directory(&self, version: usize, target: &JavaTarget) -> Option<String> {
+        self.release(version, target).ok()?.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{http::testing::serve, java::JavaUtil};
+
+    #[test]
+    fn release_is_resolved_once() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let base = serve(move |request| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            if request
+                .path
+                .starts_with("/?java_version=21&os=linux&arch=x64")
+            {
+                let body = json!([{
+                    "package_uuid": "uuid",
+                    "name": "zulu21.44.17-ca-jre21.0.8-linux_x64.tar.gz",
+                    "java_version": [21, 0, 8],
+                    "download_url": "https://example.com/zulu.tar.gz",
+                }]);
+                return (200, body.to_string());
+            }
+            assert_eq!(request.path, "/uuid");
+            (200, json!({ "sha256_hash": "abc", "size": 42 }).to_string())
+        });
+        let mut java = JavaUtil::new()
+            .with_target(JavaTarget::new(OperatingSystem::Linux, "x86_64"))
+            .with_system_java(false)
+            .with_provider(Box::new(Zulu::new().with_base_url(&base)));
+        java.set_distribution("zulu");
+        assert_eq!(
+            java.id_of(21).as_deref(),
+            Some("zulu21.44.17-ca-jre21.0.8-linux_x64")
+        );
+        let release = Zulu::new()
+            .with_base_url(&base)
+            .release(21, &java.target)
+            .unwrap();
+        assert_eq!(release.sha256, "abc");
+        assert_eq!(release.size, Some(42));
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+        // the provider of `java` already knows the package
+        let dir = std::env::temp_dir().join(format!("mcd-zulu-{}", std::process::id()));
+        let files = java.fetch(21, &dir.to_string_lossy()).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+    }
+}
@@ -0,0 +1,223 @@
+use std::fs;
+
+use tracing::debug;
+
+use crate::{errors::JvmError, os::system::OperatingSystem};
+
+/// Heap size, resolved to MiB when the preset is applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeapSize {
+    Mebibytes(u64),
+    /// Percentage of the system memory
+    Percent(u8),
+}
+impl HeapSize {
+    /// Parses `4G`, `512M`, `2048` (MiB) or `50%`
+    pub fn parse(value: &str) -> Result<Self, JvmError> {
+        let invalid = || JvmError::InvalidHeap(value.to_owned());
+        let value = value.trim();
+        if let Some(percent) = value.strip_suffix('%') {
+            let percent: u8 = percent.parse().map_err(|_| invalid())?;
+            if percent == 0 || percent > 100 {
+                return Err(invalid());
+            }
+            return Ok(HeapSize::Percent(percent));
+        }
+        let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+            Some(index) => value.split_at(index),
+            None => (value, "M"),
+        };
+        let number: u64 = number.parse().map_err(|_| invalid())?;
+        let mebibytes = match unit {
+            "M" | "m" => number,
+            "G" | "g" => number.checked_mul(1024).ok_or_else(invalid)?,
+            _ => return Err(invalid()),
+        };
+        if mebibytes == 0 {
+            return Err(invalid());
+        }
+        Ok(HeapSize::Mebibytes(mebibytes))
+    }
+    pub fn mebibytes(&self) -> Result<u64, JvmError> {
+        match self {
+            HeapSize::Mebibytes(mebibytes) => Ok(*mebibytes),
+            HeapSize::Percent(percent) => {
+                Ok((total_memory()? / 1024 / 1024 * *percent as u64 / 100).max(1))
+            }
+        }
+    }
+}
+
+/// Total memory of the system in bytes, `MemoryUnavailable` on unknown systems
+pub fn total_memory() -> Result<u64, JvmError> {
+    match OperatingSystem::detect() {
+        OperatingSystem::Linux => {
+            let meminfo = fs::read_to_string("/proc/meminfo")?;
+            meminfo
+                .lines()
+                .find_map(|line| line.strip_prefix("MemTotal:"))
+                .and_then(|line| {
+                    line.trim()
+                        .trim_end_matches("kB")
+                        .trim()
+                        .parse::<u64>()
+                        .ok()
+                })
+                .map(|kib| kib * 1024)
+                .ok_or(JvmError::MemoryUnavailable())
+        }
+        OperatingSystem::MacOS => {
+            let output = std::process::Command::new("sysctl")
+                .args(["-n", "hw.memsize"])
+                .output()?;
+            String::from_utf8_lossy(&output.stdout)
+                .trim()
+                .parse()
+                .map_err(|_| JvmError::MemoryUnavailable())
+        }
+        OperatingSystem::Windows => {
+            let output = std::process::Command::new("powershell")
+                .args([
+                    "-NoProfile",
+                    "-Command",
+                    "(Get-CimInstance Win32_ComputerSystem).TotalPhysicalMemory",
+                ])
+                .output()?;
+            String::from_utf8_lossy(&output.stdout)
+                .trim()
+                .parse()
+                .map_err(|_| JvmError::MemoryUnavailable())
+        }
+        OperatingSystem::Other => Err(JvmError::MemoryUnavailable()),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GcProfile {
+    /// Whatever the jvm picks
+    #[default]
+    Default,
+    /// G1 with short pauses and a large young generation
+    G1Tuned,
+    /// Concurrent collector, Java 17+
+    Zgc,
+}
+
+/// Flag of a profile with the Java versions that accept it
+struct JvmFlag {
+    flag: &'static str,
+    min: usize,
+    max: usize,
+}
+const fn flag(flag: &'static str, min: usize, max: usize) -> JvmFlag {
+    JvmFlag { flag, min, max }
+}
+
+const G1_TUNED: &[JvmFlag] = &[
+    flag("-XX:+UseG1GC", 8, usize::MAX),
+    flag("-XX:+ParallelRefProcEnabled", 8, usize::MAX),
+    flag("-XX:MaxGCPauseMillis=200", 8, usize::MAX),
+    flag("-XX:+UnlockExperimentalVMOptions", 8, usize::MAX),
+    flag("-XX:+DisableExplicitGC", 8, usize::MAX),
+    flag("-XX:+AlwaysPreTouch", 8, usize::MAX),
+    flag("-XX:G1NewSizePercent=30", 8, usize::MAX),
+    flag("-XX:G1MaxNewSizePercent=40", 8, usize::MAX),
+    flag("-XX:G1HeapRegionSize=8M", 8, usize::MAX),
+    flag("-XX:G1ReservePercent=20", 8, usize::MAX),
+    flag("-XX:G1HeapWastePercent=5", 8, usize::MAX),
+    flag("-XX:G1MixedGCCountTarget=4", 8, usize::MAX),
+    flag("-XX:InitiatingHeapOccupancyPercent=15", 8, usize::MAX),
+    flag("-XX:G1MixedGCLiveThresholdPercent=90", 8, usize::MAX),
+    flag("-XX:SurvivorRatio=32", 8, usize::MAX),
+    flag("-XX:+PerfDisableSharedMem", 8, usize::MAX),
+    flag("-XX:MaxTenuringThreshold=1", 8, usize::MAX),
+];
+const ZGC: &[JvmFlag] = &[
+    flag("-XX:+UseZGC", 17, usize::MAX),
+    // generational mode is the only one from 23 on, the flag is obsolete there
+    flag("-XX:+ZGenerational", 21, 22),
+    flag("-XX:+DisableExplicitGC", 8, usize::MAX),
+    flag("-XX:+AlwaysPreTouch", 8, usize::MAX),
+    flag("-XX:+PerfDisableSharedMem", 8, usize::MAX),
+];
+
+impl GcProfile {
+    /// Oldest Java version the profile runs on
+    pub fn min_java(&self) -> usize {
+        match self {
+            GcProfile::Default | GcProfile::G1Tuned => 8,
+            GcProfile::Zgc => 17,
+        }
+    }
+    fn flags(&self) -> &'static [JvmFlag] {
+        match self {
+            GcProfile::Default => &[],
+            GcProfile::G1Tuned => G1_TUNED,
+            GcProfile::Zgc => ZGC,
+        }
+    }
+    /// Flags of the profile accepted by the Java version
+    pub fn args(&self, java: usize) -> Result<Vec<String>, JvmError> {
+        if java < self.min_java() {
+            return Err(JvmError::Unsupported(
+                format!("{:?}", self),
+                self.min_java(),
+                java,
+            ));
+        }
+        Ok(self
+            .flags()
+            .iter()
+            .filter(|flag| (flag.min..=flag.max).contains(&java))
+            .map(|flag| flag.flag.to_owned())
+            .collect())
+    }
+}
+
+/// Memory and GC tuning shared by every launch
+#[derive(Debug, Clone, Default)]
+pub struct JvmPreset {
+    pub min_heap: Option<HeapSize>,
+    pub max_heap: Option<HeapSize>,
+    pub gc: GcProfile,
+}
+impl JvmPreset {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_min_heap(mut self, size: HeapSize) -> Self {
+        self.min_heap = Some(size);
+        self
+    }
+    pub fn with_max_heap(mut self, size: HeapSize) -> Self {
+        self.max_heap = Some(size);
+        self
+    }
+    pub fn with_gc(mut self, gc: GcProfile) -> Self {
+        self.gc = gc;
+        self
+    }
+    /// Jvm options of the preset for the Java major version, as returned by `Client::java()`
+    pub fn args(&self, java: usize) -> Result<Vec<String>, JvmError> {
+        // versions without `javaVersion` run on Java 8
+        let java = java.max(8);
+        let min = self.min_heap.map(|size| size.mebibytes()).transpose()?;
+        let max = self.max_heap.map(|size| size.mebibytes()).transpose()?;
+        if let (Some(min), Some(max)) = (min, max)
+            && min > max
+        {
+            return Err(JvmError::InvalidHeap(format!("{}M > {}M", min, max)));
+        }
+
+        let mut args = Vec::new();
+        if let Some(min) = min {
+            args.push(format!("-Xms{}M", min));
+        }
+        if let Some(max) = max {
+            args.push(format!("-Xmx{}M", max));
+        }
+        args.append(&mut self.gc.args(java)?);
+        debug!("JVM PRESET FOR JAVA {}: {:?}", java, args);
+        Ok(args)
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{
+    api::{assets::Assets, client::Client},
+    auth::Session,
+    command::{Command, build_args},
+    features::Features,
+    os::system::OperatingSystem,
+    util::placeholders,
+};
+
+/// Directories of an installation, as laid out by the official launcher
+#[derive(Debug, Clone)]
+pub struct InstallLayout {
+    pub root: String,
+    pub game_directory: String,
+    pub libraries: String,
+    pub assets: String,
+    pub versions: String,
+    pub natives: String,
+}
+impl InstallLayout {
+    pub fn new(root: &str) -> Self {
+        let root = root.trim_end_matches('/');
+        InstallLayout {
+            root: root.to_owned(),
+            game_directory: root.to_owned(),
+            libraries: format!("{}/libraries", root),
+            assets: format!("{}/assets", root),
+            versions: format!("{}/versions", root),
+            natives: format!("{}/natives", root),
+        }
+    }
+    /// Separate game directory, e.g. one per instance sharing the same root
+    pub fn with_game_directory(mut self, game_directory: &str) -> Self {
+        self.game_directory = game_directory.to_owned();
+        self
+    }
+    pub fn client_jar(&self, version: &str) -> String {
+        format!("{}/{}/{}.jar", self.versions, version, version)
+    }
+    pub fn natives_directory(&self, version: &str) -> String {
+        format!("{}/{}", self.natives, version)
+    }
+    /// Where indexes with `map_to_resources` are copied, the game reads it from its directory
+    pub fn resources_directory(&self) -> String {
+        format!("{}/resources", self.game_directory)
+    }
+}
+
+/// Typed values for the placeholders of the version arguments
+#[derive(Debug, Clone)]
+pub struct LaunchContext {
+    pub layout: InstallLayout,
+    pub version_name: String,
+    pub version_type: String,
+    pub main_class: String,
+    pub assets_index_name: String,
+    /// The index copies the assets to the resources directory, see `with_assets`
+    pub map_to_resources: bool,
+    pub client_jar: String,
+    pub natives_directory: String,
+    pub classpath: Vec<String>,
+    pub launcher_name: String,
+    pub launcher_version: String,
+    /// Account filling the `auth_*` placeholders, they stay unfilled without one
+    pub session: Option<Session>,
+    pub features: Features,
+    pub log_config: Option<String>,
+    /// Values for placeholders unknown to the context, they override the derived ones
+    pub extra: HashMap<String, String>,
+}
+impl LaunchContext {
+    /// Derives the context from the version, `classpath` as returned by `LibsUtil::fetch`
+    pub fn new(client: &Client, layout: InstallLayout, classpath: Vec<String>) -> Self {
+        LaunchContext {
+            version_name: client.id.clone(),
+            version_type: client.version_type.clone(),
+            main_class: client.main_class.clone(),
+            assets_index_name: if client.assets.is_empty() {
+                client.asset_index.id.clone()
+            } else {
+                client.assets.clone()
+            },
+            map_to_resources: false,
+            client_jar: layout.client_jar(&client.id),
+            natives_directory: layout.natives_directory(&client.id),
+            layout,
+            classpath,
+            launcher_name: String::from(env!("CARGO_PKG_NAME")),
+            launcher_version: String::from(env!("CARGO_PKG_VERSION")),
+            session: None,
+            features: Features::default(),
+            log_config: None,
+            extra: HashMap::new(),
+        }
+    }
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.session = Some(session);
+        self
+    }
+    pub fn with_window(mut self, width: u32, height: u32) -> Self {
+        self.features = self.features.with_resolution(width, height);
+        self
+    }
+    pub fn with_features(mut self, features: Features) -> Self {
+        self.features = features;
+        self
+    }
+    /// Points `game_assets` to where `ResourceUtil::fetch` copied the assets of the index
+    pub fn with_assets(mut self, assets: &Assets) -> Self {
+        if !assets.id.is_empty() {
+            self.assets_index_name = assets.id.clone();
+        }
+        self.map_to_resources = assets.map_to_resources;
+        self
+    }
+    pub fn with_launcher(mut self, name: &str, version: &str) -> Self {
+        self.launcher_name = name.to_owned();
+        self.launcher_version = version.to_owned();
+        self
+    }
+    pub fn with_client_jar(mut self, path: &str) -> Self {
+        self.client_jar = path.to_owned();
+        self
+    }
+    pub fn with_natives_directory(mut self, path: &str) -> Self {
+        self.natives_directory = path.to_owned();
+        self
+    }
+    /// Path of the log4j config referenced by the logging argument
+    pub fn with_log_config(mut self, path: &str) -> Self {
+        self.log_config = Some(path.to_owned());
+        self
+    }
+    pub fn with_value(mut self, key: &str, value: &str) -> Self {
+        self.extra.insert(key.to_owned(), value.to_owned());
+        self
+    }
+    pub fn classpath_separator() -> &'static str {
+        match OperatingSystem::detect() {
+            OperatingSystem::Windows => ";",
+            _ => ":",
+        }
+    }
+    pub fn data(&self) -> HashMap<String, String> {
+        let mut data = HashMap::new();
+        let mut insert = |k: &str, v: &str| {
+            data.insert(k.to_owned(), v.to_owned());
+        };
+        let layout = &self.layout;
+
+        insert("natives_directory", &self.natives_directory);
+        insert("library_directory", &layout.libraries);
+        insert("classpath_separator", Self::classpath_separator());
+        insert("classpath", &self.full_classpath());
+        insert("primary_jar", &self.client_jar);
+        insert("main_class", &self.main_class);
+        insert("launcher_name", &self.launcher_name);
+        insert("launcher_version", &self.launcher_version);
+        insert("version_name", &self.version_name);
+        insert("version_type", &self.version_type);
+        insert("game_directory", &layout.game_directory);
+        insert("assets_root", &layout.assets);
+        insert("assets_index_name", &self.assets_index_name);
+        let game_assets = if self.map_to_resources {
+            layout.resources_directory()
+        } else {
+            format!("{}/virtual/{}", layout.assets, self.assets_index_name)
+        };
+        insert("game_assets", &game_assets);
+        insert("user_properties", "{}");
+        if let Some(log_config) = &self.log_config {
+            insert("path", log_config);
+        }
+        self.features.apply(&mut data);
+        if let Some(session) = &self.session {
+            session.apply(&mut data);
+        }
+        data.extend(self.extra.clone());
+        data
+    }
+    fn full_classpath(&self) -> String {
+        let mut classpath = self.classpath.clone();
+        if !classpath.contains(&self.client_jar) {
+            classpath.push(self.client_jar.clone());
+        }
+        classpath.join(Self::classpath_separator())
+    }
+    /// Placeholders referenced by the arguments that the context doesn't fill
+    pub fn unfilled(&self, game: &[String], jvm: &[String]) -> Vec<String> {
+        let data = self.data();
+        let keys: BTreeSet<String> = game
+            .iter()
+            .chain(jvm.iter())
+            .flat_map(|arg| placeholders(arg))
+            .filter(|key| !data.contains_key(key))
+            .collect();
+        keys.into_iter().collect()
+    }
+    /// Arguments of the version for the features of the context
+    pub fn args(&self, client: &Client) -> (Vec<String>, Vec<String>) {
+        build_args(client, &self.features)
+    }
+    pub fn command(&self, game: Vec<String>, jvm: Vec<String>) -> Command {
+        Command::from_args(game, jvm, self.data())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{auth::offline::OfflineAccount, errors::FillingError};
+
+    fn context() -> LaunchContext {
+        let client: Client = serde_json::from_str(
+            r#"{"id":"1.21.1","libraries":[],"mainClass":"net.minecraft.client.main.Main",
+                "releaseTime":"2024-08-08","time":"2024-08-08","type":"release"}"#,
+        )
+        .unwrap();
+        LaunchContext::new(&client, InstallLayout::new("/mc"), Vec::new())
+    }
+
+    fn args() -> Vec<String> {
+        "--username ${auth_player_name} --uuid ${auth_uuid} --accessToken ${auth_access_token}"
+            .split(' ')
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn account_placeholders_unfilled_without_session() {
+        let context = context();
+        assert_eq!(
+            context.unfilled(&args(), &[]),
+            ["auth_access_token", "auth_player_name", "auth_uuid"]
+        );
+        let result = context.command(args(), Vec::new()).launch();
+        assert!(matches!(result, Err(FillingError::MissingKeys(keys)) if keys.len() == 3));
+    }
+
+    #[test]
+    fn session_fills_account_placeholders() {
+        let account = OfflineAccount::new("Notch").unwrap();
+        let context = context().with_session(account.session());
+        assert!(context.unfilled(&args(), &[]).is_empty());
+        let launch = context.command(args(), Vec::new()).launch().unwrap();
+        assert_eq!(launch.game[1], "Notch");
+        assert_eq!(launch.game[3], account.uuid);
+        assert_eq!(launch.main_class, "net.minecraft.client.main.Main");
+    }
+}
@@ -1,10 +1,21 @@
 pub mod api;
+pub mod asset_filter;
+pub mod auth;
 pub mod command;
+pub mod crash;
 pub mod errors;
+pub mod features;
 pub mod file;
+pub(crate) mod http;
 pub mod java;
+pub mod jvm;
+pub mod launch;
 pub mod libs;
+pub mod logs;
 pub(crate) mod log_indicator;
-pub(crate) mod os;
+pub mod os;
+pub mod process;
 pub mod resource;
+pub mod shell;
+pub mod store;
 pub mod util;
@@ -23,7 +23,7 @@ impl MavenLibrary {
 
         MavenLibrary {
             repository,
-            group_id: tokens.get(0).unwrap().to_string(),
+            group_id: tokens.first().unwrap().to_string(),
             artifact_id: tokens.get(1).unwrap().to_string(),
             version: tokens.get(2).unwrap().to_string(),
         }
@@ -57,6 +57,11 @@ impl MavenLibrary {
 
 pub struct LibsUtil;
 
+impl Default for LibsUtil {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl LibsUtil {
     pub fn new() -> LibsUtil {
         LibsUtil {}
@@ -112,12 +117,12 @@ impl LibsUtil {
             debug!("Find native classifier... {}", native_key.as_str());
             if let Some(native) = &classifier.clone().unwrap().get(&native_key) {
                 debug!("Download allowed...");
-                return Self::add_classifier(destination, binary_destination, native);
+                Self::add_classifier(destination, binary_destination, native)
             } else {
-                return Err(ClassifierError::NoNativeClassifier());
+                Err(ClassifierError::NoNativeClassifier())
             }
         } else {
-            return Err(ClassifierError::NoClassifier());
+            Err(ClassifierError::NoClassifier())
         }
     }
 
@@ -184,7 +189,7 @@ impl LibsUtil {
                 _ => {}
             }
         }
-        return String::new();
+        String::new()
     }
 }
 #[derive(Error, Debug)]
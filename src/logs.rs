@@ -0,0 +1,420 @@
+use std::{collections::HashMap, fmt::Display};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+    Unknown,
+}
+impl LogLevel {
+    pub fn parse(level: &str) -> Self {
+        match level.trim().to_ascii_uppercase().as_str() {
+            "TRACE" | "FINEST" | "FINER" => LogLevel::Trace,
+            "DEBUG" | "FINE" => LogLevel::Debug,
+            "INFO" | "CONFIG" => LogLevel::Info,
+            "WARN" | "WARNING" => LogLevel::Warn,
+            "ERROR" | "SEVERE" => LogLevel::Error,
+            "FATAL" => LogLevel::Fatal,
+            _ => LogLevel::Unknown,
+        }
+    }
+}
+impl Display for LogLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            LogLevel::Trace => "TRACE",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+            LogLevel::Fatal => "FATAL",
+            LogLevel::Unknown => "UNKNOWN",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogTimestamp {
+    /// Milliseconds since the epoch, written by the XML layout
+    Epoch(u64),
+    /// Time as printed by the plain text layouts
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEvent {
+    pub timestamp: Option<LogTimestamp>,
+    pub level: LogLevel,
+    pub thread: Option<String>,
+    pub logger: Option<String>,
+    pub message: String,
+    pub throwable: Option<String>,
+}
+
+/// What a line of output adds to the log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogUpdate {
+    Event(LogEvent),
+    /// Line of a plain text stack trace, belongs to the `throwable` of the last event
+    StackTrace(String),
+}
+
+/// Incremental parser for the game output, fed line by line
+///
+/// Handles the log4j `XMLLayout` used when the logging config is applied and the
+/// plain text formats of older versions or of clients started without it. Events are
+/// returned as soon as they are complete, plain stack traces follow as `StackTrace` lines.
+#[derive(Default)]
+pub struct LogParser {
+    event: Option<String>,
+    /// Whether stack trace lines have an event to belong to
+    has_event: bool,
+}
+impl LogParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Events of the whole text, with the stack traces in their `throwable`
+    pub fn parse(text: &str) -> Vec<LogEvent> {
+        let mut parser = Self::new();
+        let mut events: Vec<LogEvent> = Vec::new();
+        for update in text.lines().filter_map(|l| parser.push(l)) {
+            match update {
+                LogUpdate::Event(event) => events.push(event),
+                LogUpdate::StackTrace(line) => {
+                    if let Some(event) = events.last_mut() {
+                        append_stack_trace(event, &line);
+                    }
+                }
+            }
+        }
+        events.extend(parser.finish());
+        events
+    }
+    /// Adds a line of output, returns what it completes
+    pub fn push(&mut self, line: &str) -> Option<LogUpdate> {
+        if let Some(event) = self.event.as_mut() {
+            event.push('\n');
+            event.push_str(line);
+            if line.contains("</log4j:Event>") {
+                let event = self.event.take().unwrap();
+                return self.emit(parse_xml(&event));
+            }
+            return None;
+        }
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("<log4j:Event") {
+            if trimmed.contains("</log4j:Event>") {
+                return self.emit(parse_xml(trimmed));
+            }
+            self.event = Some(trimmed.to_owned());
+            return None;
+        }
+        if trimmed.is_empty() {
+            return None;
+        }
+        if self.has_event && is_stack_trace(line) {
+            return Some(LogUpdate::StackTrace(line.to_owned()));
+        }
+        self.emit(parse_plain(line))
+    }
+    /// Flushes an unterminated XML event, e.g. when the game was killed mid write
+    pub fn finish(&mut self) -> Option<LogEvent> {
+        self.event.take().map(|event| parse_xml(&event))
+    }
+    fn emit(&mut self, event: LogEvent) -> Option<LogUpdate> {
+        self.has_event = true;
+        Some(LogUpdate::Event(event))
+    }
+}
+
+/// Adds a `StackTrace` line to the throwable of the event
+pub fn append_stack_trace(event: &mut LogEvent, line: &str) {
+    let throwable = event.throwable.get_or_insert_with(String::new);
+    if !throwable.is_empty() {
+        throwable.push('\n');
+    }
+    throwable.push_str(line);
+}
+
+/// Line of a stack trace printed after a log line: the exception, its frames and causes
+fn is_stack_trace(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    if line.starts_with(char::is_whitespace) && trimmed.starts_with("at ")
+        || trimmed.starts_with("Caused by: ")
+        || trimmed.starts_with("Suppressed: ")
+        || trimmed.starts_with("... ")
+    {
+        return true;
+    }
+    // java.lang.IllegalStateException: message
+    let class = trimmed.split(':').next().unwrap_or(trimmed);
+    !class.contains(char::is_whitespace)
+        && class.contains('.')
+        && ["Exception", "Error", "Throwable"]
+            .iter()
+            .any(|suffix| class.ends_with(suffix))
+}
+
+fn parse_xml(event: &str) -> LogEvent {
+    let head_end = event.find('>').unwrap_or(event.len());
+    let attributes = parse_attributes(&event[..head_end]);
+    LogEvent {
+        timestamp: attributes
+            .get("timestamp")
+            .and_then(|t| t.parse().ok())
+            .map(LogTimestamp::Epoch),
+        level: attributes
+            .get("level")
+            .map(|l| LogLevel::parse(l))
+            .unwrap_or(LogLevel::Unknown),
+        thread: attributes.get("thread").cloned(),
+        logger: attributes.get("logger").cloned(),
+        message: element(event, "log4j:Message").unwrap_or_default(),
+        throwable: element(event, "log4j:Throwable"),
+    }
+}
+
+fn parse_attributes(head: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    let mut rest = head;
+    while let Some(eq) = rest.find("=\"") {
+        let key = rest[..eq]
+            .rsplit(|c: char| c.is_whitespace())
+            .next()
+            .unwrap_or("");
+        let value_start = eq + 2;
+        let Some(len) = rest[value_start..].find('"') else {
+            break;
+        };
+        let value = &rest[value_start..value_start + len];
+        attributes.insert(key.to_owned(), unescape(value));
+        rest = &rest[value_start + len + 1..];
+    }
+    attributes
+}
+
+fn element(event: &str, name: &str) -> Option<String> {
+    let open = format!("<{}>", name);
+    let close = format!("</{}>", name);
+    let start = event.find(&open)? + open.len();
+    let end = event[start..]
+        .find(&close)
+        .map(|e| start + e)
+        .unwrap_or(event.len());
+    let content = event[start..end].trim();
+    if let Some(cdata) = content.strip_prefix("<![CDATA[") {
+        let cdata = cdata.strip_suffix("]]>").unwrap_or(cdata);
+        return Some(cdata.to_owned());
+    }
+    Some(unescape(content))
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#10;", "\n")
+        .replace("&#13;", "\r")
+        .replace("&amp;", "&")
+}
+
+fn parse_plain(line: &str) -> LogEvent {
+    let mut event = LogEvent {
+        timestamp: None,
+        level: LogLevel::Unknown,
+        thread: None,
+        logger: None,
+        message: line.to_owned(),
+        throwable: None,
+    };
+
+    // modern: [12:34:56] [Render thread/INFO]: message
+    // forge:  [12:34:56] [main/INFO] [minecraft/Minecraft]: message
+    if let Some((time, rest)) = bracket(line)
+        && let Some((source, rest)) = bracket(rest.trim_start())
+        && let Some((thread, level)) = source.rsplit_once('/')
+    {
+        event.timestamp = Some(LogTimestamp::Text(time.to_owned()));
+        event.thread = Some(thread.to_owned());
+        event.level = LogLevel::parse(level);
+        let mut rest = rest.trim_start();
+        if let Some((logger, after)) = bracket(rest) {
+            event.logger = Some(logger.to_owned());
+            rest = after;
+        }
+        let rest = rest.strip_prefix(':').unwrap_or(rest);
+        event.message = rest.trim_start().to_owned();
+        return event;
+    }
+
+    // legacy: 2013-07-01 12:34:56 [INFO] [Minecraft-Client] message
+    let mut parts = line.splitn(3, ' ');
+    if let (Some(date), Some(time), Some(rest)) = (parts.next(), parts.next(), parts.next())
+        && is_date(date)
+        && time.contains(':')
+        && let Some((level, rest)) = bracket(rest)
+    {
+        event.timestamp = Some(LogTimestamp::Text(format!("{} {}", date, time)));
+        event.level = LogLevel::parse(level);
+        let mut rest = rest.trim_start();
+        if let Some((logger, after)) = bracket(rest) {
+            event.logger = Some(logger.to_owned());
+            rest = after.trim_start();
+        }
+        event.message = rest.to_owned();
+        return event;
+    }
+    event
+}
+
+fn bracket(text: &str) -> Option<(&str, &str)> {
+    let text = text.strip_prefix('[')?;
+    let end = text.find(']')?;
+    Some((&text[..end], &text[end + 1..]))
+}
+
+fn is_date(text: &str) -> bool {
+    text.len() == 10 && text.chars().all(|c| c.is_ascii_digit() || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XML: &str = r#"<log4j:Event logger="net.minecraft.client.Minecraft" timestamp="1723111111000" level="WARN" thread="Render thread">
+  <log4j:Message><![CDATA[Missing sound for event: minecraft:item.goat_horn]]></log4j:Message>
+  <log4j:Throwable><![CDATA[java.lang.IllegalStateException: boom
+	at a.b.C.d(C.java:1)]]></log4j:Throwable>
+</log4j:Event>"#;
+
+    #[test]
+    fn xml_event() {
+        let events = LogParser::parse(XML);
+        assert_eq!(
+            events,
+            [LogEvent {
+                timestamp: Some(LogTimestamp::Epoch(1723111111000)),
+                level: LogLevel::Warn,
+                thread: Some(String::from("Render thread")),
+                logger: Some(String::from("net.minecraft.client.Minecraft")),
+                message: String::from("Missing sound for event: minecraft:item.goat_horn"),
+                throwable: Some(String::from(
+                    "java.lang.IllegalStateException: boom\n\tat a.b.C.d(C.java:1)"
+                )),
+            }]
+        );
+    }
+
+    #[test]
+    fn xml_event_is_returned_once_closed() {
+        let mut parser = LogParser::new();
+        let mut lines = XML.lines();
+        let last = lines.next_back().unwrap();
+        assert!(lines.all(|line| parser.push(line).is_none()));
+        assert!(matches!(parser.push(last), Some(LogUpdate::Event(_))));
+    }
+
+    #[test]
+    fn single_line_xml_escapes() {
+        let line = r#"<log4j:Event logger="a" timestamp="1" level="INFO" thread="main"><log4j:Message>a &lt;b&gt; &amp; c</log4j:Message></log4j:Event>"#;
+        let events = LogParser::parse(line);
+        assert_eq!(events[0].message, "a <b> & c");
+        assert_eq!(events[0].throwable, None);
+    }
+
+    #[test]
+    fn unterminated_xml_event_is_flushed() {
+        let mut parser = LogParser::new();
+        assert!(
+            parser
+                .push(r#"<log4j:Event level="ERROR" thread="main">"#)
+                .is_none()
+        );
+        assert!(parser.push("<log4j:Message>cut").is_none());
+        let event = parser.finish().unwrap();
+        assert_eq!(event.level, LogLevel::Error);
+        assert_eq!(event.message, "cut");
+    }
+
+    #[test]
+    fn plain_formats() {
+        let events = LogParser::parse(
+            "[12:34:56] [Render thread/INFO]: Setting user: Notch\n\
+             [12:34:57] [main/WARN] [minecraft/Minecraft]: forge line\n\
+             2013-07-01 12:34:56 [SEVERE] [Minecraft-Client] legacy line\n\
+             something else",
+        );
+        assert_eq!(events.len(), 4);
+        assert_eq!(
+            events[0].timestamp,
+            Some(LogTimestamp::Text(String::from("12:34:56")))
+        );
+        assert_eq!(events[0].thread.as_deref(), Some("Render thread"));
+        assert_eq!(events[0].level, LogLevel::Info);
+        assert_eq!(events[0].message, "Setting user: Notch");
+        assert_eq!(events[1].logger.as_deref(), Some("minecraft/Minecraft"));
+        assert_eq!(events[1].message, "forge line");
+        assert_eq!(
+            events[2].timestamp,
+            Some(LogTimestamp::Text(String::from("2013-07-01 12:34:56")))
+        );
+        assert_eq!(events[2].level, LogLevel::Error);
+        assert_eq!(events[2].logger.as_deref(), Some("Minecraft-Client"));
+        assert_eq!(events[2].message, "legacy line");
+        assert_eq!(events[3].level, LogLevel::Unknown);
+        assert_eq!(events[3].message, "something else");
+    }
+
+    #[test]
+    fn plain_events_are_returned_immediately() {
+        let mut parser = LogParser::new();
+        let Some(LogUpdate::Event(event)) = parser.push("[12:00:00] [main/ERROR]: Crashed") else {
+            panic!("event not returned");
+        };
+        assert_eq!(event.message, "Crashed");
+        assert_eq!(
+            parser.push("java.lang.NullPointerException: x"),
+            Some(LogUpdate::StackTrace(String::from(
+                "java.lang.NullPointerException: x"
+            )))
+        );
+        assert!(matches!(
+            parser.push("\tat a.b.C.d(C.java:1)"),
+            Some(LogUpdate::StackTrace(_))
+        ));
+    }
+
+    #[test]
+    fn plain_stack_traces_join_the_event() {
+        let events = LogParser::parse(
+            "[12:00:00] [main/ERROR]: Crashed\n\
+             java.lang.RuntimeException: outer\n\
+             \tat a.b.C.d(C.java:1)\n\
+             \t... 3 more\n\
+             Caused by: java.io.IOException: inner\n\
+             [12:00:01] [main/INFO]: Stopping",
+        );
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[0].throwable.as_deref(),
+            Some(
+                "java.lang.RuntimeException: outer\n\tat a.b.C.d(C.java:1)\n\t... 3 more\n\
+                 Caused by: java.io.IOException: inner"
+            )
+        );
+        assert_eq!(events[1].throwable, None);
+    }
+
+    #[test]
+    fn stack_trace_without_event() {
+        let events = LogParser::parse("java.lang.RuntimeException: first line");
+        assert_eq!(events[0].message, "java.lang.RuntimeException: first line");
+    }
+}
@@ -1,5 +1,5 @@
 pub mod system {
-    #[derive(Debug)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub enum OperatingSystem {
         Linux,
         Windows,
@@ -0,0 +1,253 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read},
+    path::PathBuf,
+    process::{Child, ExitStatus, Stdio},
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use tracing::debug;
+
+use crate::errors::CommandError;
+
+/// Lines `GameProcess::output` holds until they are received, later lines are dropped
+/// from the channel while it is full. `on_output` gets every line
+pub const OUTPUT_BUFFER: usize = 1024;
+
+/// Line written by the game to one of its output streams
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameOutput {
+    Stdout(String),
+    Stderr(String),
+}
+impl GameOutput {
+    pub fn line(&self) -> &str {
+        match self {
+            GameOutput::Stdout(line) => line,
+            GameOutput::Stderr(line) => line,
+        }
+    }
+}
+
+/// Settings used to spawn the java process
+#[derive(Clone)]
+pub struct ProcessOptions {
+    pub java: String,
+    pub working_dir: Option<PathBuf>,
+    pub env: HashMap<String, String>,
+    pub on_output: Option<Arc<dyn Fn(GameOutput) + Send + Sync>>,
+}
+impl ProcessOptions {
+    pub fn new(java: &str) -> Self {
+        ProcessOptions {
+            java: java.to_owned(),
+            working_dir: None,
+            env: HashMap::new(),
+            on_output: None,
+        }
+    }
+    pub fn with_working_dir(mut self, dir: &str) -> Self {
+        self.working_dir = Some(PathBuf::from(dir));
+        self
+    }
+    pub fn with_env(mut self, key: &str, value: &str) -> Self {
+        self.env.insert(key.to_owned(), value.to_owned());
+        self
+    }
+    pub fn with_on_output(mut self, on_output: Arc<dyn Fn(GameOutput) + Send + Sync>) -> Self {
+        self.on_output = Some(on_output);
+        self
+    }
+    pub fn spawn(&self, args: &[String]) -> Result<GameProcess, CommandError> {
+        let mut command = std::process::Command::new(&self.java);
+        command
+            .args(args)
+            .envs(&self.env)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if let Some(dir) = &self.working_dir {
+            command.current_dir(dir);
+        }
+        debug!("SPAWN {} {:?}", self.java, args);
+        let mut child = command.spawn()?;
+
+        let (sender, output) = mpsc::sync_channel(OUTPUT_BUFFER);
+        let mut readers = Vec::new();
+        if let Some(stdout) = child.stdout.take() {
+            readers.push(forward(
+                stdout,
+                GameOutput::Stdout,
+                sender.clone(),
+                self.on_output.clone(),
+            ));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            readers.push(forward(
+                stderr,
+                GameOutput::Stderr,
+                sender,
+                self.on_output.clone(),
+            ));
+        }
+
+        Ok(GameProcess {
+            handle: ProcessHandle {
+                pid: child.id(),
+                child: Arc::new(Mutex::new(child)),
+            },
+            output,
+            readers,
+        })
+    }
+}
+
+fn forward<R: Read + Send + 'static>(
+    stream: R,
+    wrap: fn(String) -> GameOutput,
+    sender: SyncSender<GameOutput>,
+    on_output: Option<Arc<dyn Fn(GameOutput) + Send + Sync>>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(stream);
+        let mut buffer = Vec::new();
+        let mut dropped = 0;
+        loop {
+            buffer.clear();
+            // lines aren't always utf-8, e.g. on Windows codepages
+            match reader.read_until(b'\n', &mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            let text = String::from_utf8_lossy(&buffer);
+            let line = wrap(text.trim_end_matches(['\n', '\r']).to_owned());
+            if let Some(callback) = &on_output {
+                callback(line.clone());
+            }
+            // the receiver may be full or dropped, the callback still gets the lines
+            if let Err(TrySendError::Full(_)) = sender.try_send(line) {
+                dropped += 1;
+            }
+        }
+        if dropped > 0 {
+            debug!("DROPPED {} OUTPUT LINES, THE CHANNEL WAS FULL", dropped);
+        }
+    })
+}
+
+/// Cloneable handle used to query or stop the game from another thread
+#[derive(Clone)]
+pub struct ProcessHandle {
+    pid: u32,
+    child: Arc<Mutex<Child>>,
+}
+impl ProcessHandle {
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+    pub fn try_wait(&self) -> Result<Option<ExitStatus>, CommandError> {
+        Ok(self.child.lock().unwrap().try_wait()?)
+    }
+    pub fn is_running(&self) -> bool {
+        matches!(self.try_wait(), Ok(None))
+    }
+    pub fn kill(&self) -> Result<(), CommandError> {
+        let mut child = self.child.lock().unwrap();
+        if child.try_wait()?.is_some() {
+            return Ok(());
+        }
+        debug!("KILL {}", self.pid);
+        Ok(child.kill()?)
+    }
+}
+
+/// Running game started by [`ProcessOptions::spawn`]
+pub struct GameProcess {
+    handle: ProcessHandle,
+    output: Receiver<GameOutput>,
+    readers: Vec<JoinHandle<()>>,
+}
+impl GameProcess {
+    pub fn pid(&self) -> u32 {
+        self.handle.pid()
+    }
+    pub fn handle(&self) -> ProcessHandle {
+        self.handle.clone()
+    }
+    /// Lines of stdout and stderr, the channel closes when both streams end. Holds up to
+    /// `OUTPUT_BUFFER` lines that weren't received yet
+    pub fn output(&self) -> &Receiver<GameOutput> {
+        &self.output
+    }
+    pub fn try_wait(&self) -> Result<Option<ExitStatus>, CommandError> {
+        self.handle.try_wait()
+    }
+    pub fn kill(&self) -> Result<(), CommandError> {
+        self.handle.kill()
+    }
+    /// Blocks until the game exits, polling so the handle can still kill it meanwhile
+    pub fn wait(self) -> Result<ExitStatus, CommandError> {
+        let status = loop {
+            if let Some(status) = self.handle.try_wait()? {
+                break status;
+            }
+            thread::sleep(Duration::from_millis(100));
+        };
+        for reader in self.readers {
+            let _ = reader.join();
+        }
+        debug!("EXIT {} {}", self.handle.pid(), status);
+        Ok(status)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    fn sh(script: &str, options: ProcessOptions) -> GameProcess {
+        options
+            .spawn(&[String::from("-c"), script.to_owned()])
+            .unwrap()
+    }
+
+    #[test]
+    fn invalid_utf8_keeps_the_stream_open() {
+        let process = sh("printf 'caf\\351\\r\\nafter\\n'", ProcessOptions::new("sh"));
+        let lines: Vec<GameOutput> = process.output().iter().collect();
+        assert_eq!(
+            lines,
+            [
+                GameOutput::Stdout(String::from("caf\u{fffd}")),
+                GameOutput::Stdout(String::from("after"))
+            ]
+        );
+        assert!(process.wait().unwrap().success());
+    }
+
+    #[test]
+    fn unread_output_is_bounded() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let counter = count.clone();
+        let options = ProcessOptions::new("sh").with_on_output(Arc::new(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }));
+        let total = OUTPUT_BUFFER * 2;
+        let process = sh(&format!("seq {}", total), options);
+        // nothing is received until the game exited and both streams were read
+        for reader in &process.readers {
+            while !reader.is_finished() {
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+        assert_eq!(process.output().try_iter().count(), OUTPUT_BUFFER);
+        assert_eq!(count.load(Ordering::SeqCst), total);
+    }
+}
@@ -1,62 +1,193 @@
-use std::{fs, path::Path};
+use std::{fs, path::Path, sync::Arc};
 
-use dwldutil::{DLFile, DLHashes, Downloader, cas::DLStorage};
+use dwldutil::{DLFile, DLHashType, DLHashes};
+use tracing::{debug, error, warn};
 
 use crate::{
-    api::{assets::Assets, client::Client},
-    errors::FetchError,
-    log_indicator,
+    api::{
+        assets::Assets,
+        client::{AssetsIndex, Client},
+    },
+    asset_filter::{AssetFilter, FilterReport},
+    errors::{FetchError, HttpError},
+    http,
+    launch::InstallLayout,
 };
 
 pub const BASE_URL: &str = "https://resources.download.minecraft.net";
 
 pub struct ResourceUtil<'a> {
     pub url: &'a str,
+    /// Assets `fetch` installs, every asset when unset
+    pub filter: Option<AssetFilter>,
+}
+impl Default for ResourceUtil<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 impl<'a> ResourceUtil<'a> {
     pub fn new() -> ResourceUtil<'a> {
-        ResourceUtil { url: BASE_URL }
-    }
-    pub fn index_of(&self, client: &Client, path: &str) -> Result<Assets, FetchError> {
-        let indexes = client.asset_index.clone();
-        let dl = Downloader::<log_indicator::LogIndicator>::new()
-            .add_file(DLFile::new().with_url(&indexes.url).with_path(path));
-        dl.start();
-        let content = std::fs::read_to_string(path)?;
-        Ok(serde_json::from_str(&content.as_str())?)
-    }
-    pub fn fetch(&self, assets: &Assets, destination: &str) -> Result<Vec<DLFile>, FetchError> {
-        let obj_path_str = format!("{}/objects", destination);
-        let obj_path = Path::new(&obj_path_str);
-        if !obj_path.exists() {
-            fs::create_dir_all(obj_path)?;
+        ResourceUtil {
+            url: BASE_URL,
+            filter: None,
         }
-
-        let obj_path: String = match obj_path.canonicalize() {
-            Ok(path) => path.as_path().to_str().unwrap().to_owned(),
-            Err(_) => return Err(FetchError::CanonicalizingError(destination.to_owned())),
+    }
+    /// Installs only the assets the filter allows, the index on disk stays complete
+    pub fn with_filter(mut self, filter: AssetFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+    /// What the filter saves on the index, everything is kept without a filter
+    pub fn report(&self, assets: &Assets) -> FilterReport {
+        self.filter.clone().unwrap_or_default().apply(assets).1
+    }
+    /// Index of the version, kept in `<assets>/indexes/<id>.json`. A local copy is
+    /// reused while it matches the sha1 of the version, otherwise it's downloaded again
+    pub fn index_of(&self, client: &Client, layout: &InstallLayout) -> Result<Assets, FetchError> {
+        let index = &client.asset_index;
+        if index.url.is_empty() {
+            return Err(FetchError::UrlNotFound(format!(
+                "asset index of {}",
+                client.id
+            )));
+        }
+        let path = format!("{}/indexes/{}.json", layout.assets, index.id);
+        let content = match cached_index(index, &path) {
+            Some(content) => {
+                debug!("ASSET INDEX {} CACHED", index.id);
+                content
+            }
+            None => download_index(index, &path)?,
         };
-        let storage = DLStorage::new(obj_path.as_str());
+        let mut assets: Assets = serde_json::from_str(&content)?;
+        assets.id = index.id.clone();
+        Ok(assets)
+    }
+    /// Files of the objects missing from `<assets>/objects`, an object already downloaded
+    /// is only copied to the virtual and resources directories that lack it
+    pub fn fetch(
+        &self,
+        assets: &Assets,
+        layout: &InstallLayout,
+    ) -> Result<Vec<DLFile>, FetchError> {
+        let filtered;
+        let assets = match &self.filter {
+            Some(filter) => {
+                let report;
+                (filtered, report) = filter.apply(assets);
+                debug!(
+                    "ASSET FILTER SKIPS {} KEYS, {} OF {} BYTES ({:.1}%)",
+                    report.skipped_keys,
+                    report.skipped_bytes,
+                    report.kept_bytes + report.skipped_bytes,
+                    report.saved_percent()
+                );
+                &filtered
+            }
+            None => assets,
+        };
+        let mut copy_roots = Vec::new();
+        if assets.is_virtual {
+            let id = if assets.id.is_empty() {
+                "legacy"
+            } else {
+                assets.id.as_str()
+            };
+            copy_roots.push(format!("{}/virtual/{}", layout.assets, id));
+        }
+        if assets.map_to_resources {
+            copy_roots.push(layout.resources_directory());
+        }
+
         let mut files = Vec::new();
         for (key, value) in &assets.objects {
-            let hash = &value.hash;
-            let block = &hash[..2];
-            let url = format!("{}/{}/{}", self.url, block, hash);
-            let key_path = key.as_str();
-            if !std::path::Path::new(format!("{}/virtual/legacy/", destination).as_str()).exists() {
-                fs::create_dir_all(format!("{}/virtual/legacy/", destination))?;
-            }
-            let path = format!("{}/virtual/legacy/{}", destination, key_path);
-            if !Path::new(&path).exists() {
-                let file = DLFile::new()
-                    .with_url(&url)
-                    .with_path(&path)
-                    .with_size(value.size)
-                    .with_hashes(DLHashes::new().sha1(hash))
-                    .with_cas(storage.clone());
-                files.push(file);
+            let path = format!("{}/objects/{}", layout.assets, value.path());
+            let copies: Vec<String> = copy_roots
+                .iter()
+                .map(|root| format!("{}/{}", root, key))
+                .collect();
+            // objects are content addressed, a file of the right size is the object
+            let present = fs::metadata(&path).is_ok_and(|metadata| metadata.len() == value.size);
+            if present {
+                let missing: Vec<String> = copies
+                    .into_iter()
+                    .filter(|copy| !Path::new(copy).exists())
+                    .collect();
+                copy_object(&path, &missing);
+                continue;
             }
+            let file = DLFile::new()
+                .with_url(&format!("{}/{}", self.url, value.path()))
+                .with_path(&path)
+                .with_size(value.size)
+                .with_hashes(DLHashes::new().sha1(&value.hash));
+            files.push(if copies.is_empty() {
+                file
+            } else {
+                file.with_on_download(Arc::new(move |path| copy_object(&path, &copies)))
+            });
         }
+        debug!("ASSETS {} NEED {} FILES", assets.id, files.len());
         Ok(files)
     }
 }
+
+/// Local copy of the index, when its size and sha1 are the expected ones
+fn cached_index(index: &AssetsIndex, path: &str) -> Option<String> {
+    // without a sha1 the copy can't be trusted
+    if index.sha1.is_empty() {
+        return None;
+    }
+    let content = fs::read_to_string(path).ok()?;
+    let valid = (index.size == 0 || content.len() as u64 == index.size)
+        && DLHashType::SHA1.verify_data(content.as_bytes(), &index.sha1);
+    if !valid {
+        warn!("ASSET INDEX {} IS OUTDATED OR CORRUPTED", path);
+    }
+    valid.then_some(content)
+}
+
+/// Downloads the index, verifying it before replacing the local copy
+fn download_index(index: &AssetsIndex, path: &str) -> Result<String, FetchError> {
+    let response = http::get(&index.url, None)?;
+    if !response.is_success() {
+        return Err(HttpError::Status(response.status, response.body).into());
+    }
+    let content = response.body;
+    let size = content.len() as u64;
+    if index.size != 0 && size != index.size {
+        return Err(FetchError::SizeMismatch(
+            index.url.clone(),
+            index.size,
+            size,
+        ));
+    }
+    if !index.sha1.is_empty() && !DLHashType::SHA1.verify_data(content.as_bytes(), &index.sha1) {
+        return Err(FetchError::HashMismatch(index.url.clone()));
+    }
+    if let Some(parent) = Path::new(path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // written aside first so an interrupted write doesn't leave a broken index
+    let partial = format!("{}.tmp", path);
+    fs::write(&partial, &content)?;
+    fs::rename(&partial, path)?;
+    debug!("ASSET INDEX {} DOWNLOADED", index.id);
+    Ok(content)
+}
+
+/// Copies the object to the paths of its key
+fn copy_object(object: &str, copies: &[String]) {
+    for copy in copies {
+        if let Some(parent) = Path::new(copy).parent()
+            && let Err(e) = fs::create_dir_all(parent)
+        {
+            error!("CREATING {} FAILED: {}", parent.display(), e);
+            continue;
+        }
+        if let Err(e) = fs::copy(object, copy) {
+            error!("COPYING {} TO {} FAILED: {}", object, copy, e);
+        }
+    }
+}
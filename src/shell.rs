@@ -0,0 +1,158 @@
+use crate::os::system::OperatingSystem;
+
+/// Shell a command line is rendered for, argv vectors never need this
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Posix,
+    Cmd,
+    PowerShell,
+}
+impl Shell {
+    pub fn detect() -> Self {
+        match OperatingSystem::detect() {
+            OperatingSystem::Windows => Shell::PowerShell,
+            _ => Shell::Posix,
+        }
+    }
+    pub fn quote(&self, arg: &str) -> String {
+        match self {
+            Shell::Posix => quote_posix(arg),
+            Shell::Cmd => quote_cmd(arg),
+            Shell::PowerShell => quote_powershell(arg),
+        }
+    }
+    /// Command line that runs `program` with exactly `args` when pasted in the shell
+    pub fn render(&self, program: &str, args: &[String]) -> String {
+        let mut line = self.quote(program);
+        if *self == Shell::PowerShell && line != program {
+            // a quoted string is an expression, the call operator runs it
+            line.insert_str(0, "& ");
+        }
+        for arg in args {
+            line.push(' ');
+            line.push_str(&self.quote(arg));
+        }
+        line
+    }
+}
+
+fn is_safe(arg: &str, allowed: &str) -> bool {
+    !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || allowed.contains(c))
+}
+
+fn quote_posix(arg: &str) -> String {
+    if is_safe(arg, "_-.,/:=+@%") {
+        return arg.to_owned();
+    }
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+fn quote_powershell(arg: &str) -> String {
+    let safe = if arg.starts_with('-') {
+        // bare `-Dkey.name=value` is split at the dot before reaching the program
+        is_safe(arg, "_-")
+    } else {
+        is_safe(arg, "_-./\\:=+")
+    };
+    if safe {
+        return arg.to_owned();
+    }
+    format!("'{}'", arg.replace('\'', "''"))
+}
+
+/// Quotes for the argv parsing of the C runtime, then escapes the cmd metacharacters
+fn quote_cmd(arg: &str) -> String {
+    let mut quoted = String::new();
+    if !arg.is_empty() && !arg.contains([' ', '\t', '\n', '"']) {
+        quoted.push_str(arg);
+    } else {
+        quoted.push('"');
+        let mut backslashes = 0;
+        for c in arg.chars() {
+            match c {
+                '\\' => backslashes += 1,
+                '"' => {
+                    quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                    quoted.push('"');
+                    backslashes = 0;
+                }
+                _ => {
+                    quoted.push_str(&"\\".repeat(backslashes));
+                    quoted.push(c);
+                    backslashes = 0;
+                }
+            }
+        }
+        quoted.push_str(&"\\".repeat(backslashes * 2));
+        quoted.push('"');
+    }
+    if !quoted.contains(['(', ')', '%', '!', '^', '<', '>', '&', '|']) {
+        return quoted;
+    }
+    let mut escaped = String::with_capacity(quoted.len());
+    for c in quoted.chars() {
+        if "()%!^\"<>&|".contains(c) {
+            escaped.push('^');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn posix_quote() {
+        assert_eq!(Shell::Posix.quote("-Xmx2G"), "-Xmx2G");
+        assert_eq!(
+            Shell::Posix.quote("/opt/mc/libraries/a.jar:b.jar"),
+            "/opt/mc/libraries/a.jar:b.jar"
+        );
+        assert_eq!(Shell::Posix.quote(""), "''");
+        assert_eq!(Shell::Posix.quote("my world"), "'my world'");
+        assert_eq!(Shell::Posix.quote("it's"), "'it'\\''s'");
+        assert_eq!(Shell::Posix.quote("$HOME"), "'$HOME'");
+    }
+
+    #[test]
+    fn cmd_quote() {
+        assert_eq!(Shell::Cmd.quote("-Xmx2G"), "-Xmx2G");
+        assert_eq!(Shell::Cmd.quote(""), "\"\"");
+        assert_eq!(
+            Shell::Cmd.quote("C:\\Program Files\\"),
+            "\"C:\\Program Files\\\\\""
+        );
+        assert_eq!(Shell::Cmd.quote("say \"hi\""), "\"say \\\"hi\\\"\"");
+        assert_eq!(Shell::Cmd.quote("a\\\"b"), "\"a\\\\\\\"b\"");
+        assert_eq!(Shell::Cmd.quote("%APPDATA%"), "^%APPDATA^%");
+        assert_eq!(Shell::Cmd.quote("a & b"), "^\"a ^& b^\"");
+    }
+
+    #[test]
+    fn powershell_quote() {
+        assert_eq!(Shell::PowerShell.quote("C:\\mc\\a.jar"), "C:\\mc\\a.jar");
+        assert_eq!(Shell::PowerShell.quote("-Xmx2G"), "-Xmx2G");
+        assert_eq!(
+            Shell::PowerShell.quote("-Dlog4j.configurationFile=log.xml"),
+            "'-Dlog4j.configurationFile=log.xml'"
+        );
+        assert_eq!(Shell::PowerShell.quote(""), "''");
+        assert_eq!(Shell::PowerShell.quote("it's"), "'it''s'");
+        assert_eq!(Shell::PowerShell.quote("$env:APPDATA"), "'$env:APPDATA'");
+    }
+
+    #[test]
+    fn powershell_render_calls_quoted_program() {
+        let args = [String::from("-version")];
+        assert_eq!(Shell::PowerShell.render("java", &args), "java -version");
+        assert_eq!(
+            Shell::PowerShell.render("C:\\Program Files\\java.exe", &args),
+            "& 'C:\\Program Files\\java.exe' -version"
+        );
+    }
+}
@@ -0,0 +1,401 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fs::{self, OpenOptions},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::{
+    api::{
+        assets::Assets,
+        client::{Client, Library},
+    },
+    errors::StoreError,
+    launch::InstallLayout,
+    libs::MavenLibrary,
+};
+
+/// How long a store operation waits for the lock of another launcher
+pub const LOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Files of the store a version uses
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct VersionReferences {
+    pub asset_index: String,
+    /// Hashes of the asset objects
+    pub objects: BTreeSet<String>,
+    /// Paths inside the libraries directory
+    pub libraries: BTreeSet<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct StoreFile {
+    versions: BTreeMap<String, VersionReferences>,
+}
+
+/// Files `SharedStore::gc` removes or would remove
+#[derive(Debug, Clone, Default)]
+pub struct GcReport {
+    pub dry_run: bool,
+    pub files: Vec<PathBuf>,
+    pub reclaimable_bytes: u64,
+}
+
+/// Assets and libraries shared by every instance under the root, with the versions using
+/// them recorded in `store.json` so files no version references can be collected. Changes
+/// are made under `store.lock` on the latest `store.json`, launchers can share the root.
+///
+/// A version must be registered before its files are fetched, `gc` removes the files of
+/// versions that are being installed but not registered yet
+pub struct SharedStore {
+    pub root: PathBuf,
+    data: StoreFile,
+}
+impl SharedStore {
+    pub fn open(root: &str) -> Result<Self, StoreError> {
+        let mut store = SharedStore {
+            root: PathBuf::from(root.trim_end_matches('/')),
+            data: StoreFile::default(),
+        };
+        let _lock = store.lock()?;
+        store.reload()?;
+        Ok(store)
+    }
+    /// Layout of an instance using the store, its saves and options stay in `game_directory`
+    pub fn layout(&self, game_directory: &str) -> InstallLayout {
+        InstallLayout::new(&self.root.to_string_lossy()).with_game_directory(game_directory)
+    }
+    pub fn assets_directory(&self) -> PathBuf {
+        self.root.join("assets")
+    }
+    pub fn libraries_directory(&self) -> PathBuf {
+        self.root.join("libraries")
+    }
+    /// Versions referencing the store
+    pub fn versions(&self) -> Vec<&str> {
+        self.data.versions.keys().map(|id| id.as_str()).collect()
+    }
+    pub fn references(&self, version: &str) -> Option<&VersionReferences> {
+        self.data.versions.get(version)
+    }
+    /// Records the assets and libraries of the version, replacing its previous references.
+    /// Call it before fetching them so a concurrent `gc` keeps them
+    pub fn register(&mut self, client: &Client, assets: &Assets) -> Result<(), StoreError> {
+        let references = VersionReferences {
+            asset_index: client.asset_index.id.clone(),
+            objects: assets
+                .objects
+                .values()
+                .map(|object| object.hash.clone())
+                .collect(),
+            libraries: client.libraries.iter().flat_map(library_paths).collect(),
+        };
+        debug!(
+            "STORE REGISTER {} WITH {} OBJECTS AND {} LIBRARIES",
+            client.id,
+            references.objects.len(),
+            references.libraries.len()
+        );
+        // other launchers may have registered versions since the store was opened
+        let _lock = self.lock()?;
+        self.reload()?;
+        self.data.versions.insert(client.id.clone(), references);
+        self.save()
+    }
+    pub fn unregister(&mut self, version: &str) -> Result<(), StoreError> {
+        let _lock = self.lock()?;
+        self.reload()?;
+        if self.data.versions.remove(version).is_none() {
+            return Err(StoreError::NotRegistered(version.to_owned()));
+        }
+        self.save()
+    }
+    /// Removes the objects, indexes and libraries no version references, a dry run only
+    /// reports them. Downloads in progress (`*.tmp`) are kept. Fails when no version is
+    /// registered instead of emptying the store
+    pub fn gc(&mut self, dry_run: bool) -> Result<GcReport, StoreError> {
+        // no version can be registered while the files are collected
+        let _lock = self.lock()?;
+        self.reload()?;
+        if self.data.versions.is_empty() {
+            return Err(StoreError::NoReferences());
+        }
+        let mut objects = HashSet::new();
+        let mut indexes = HashSet::new();
+        let mut libraries = HashSet::new();
+        for references in self.data.versions.values() {
+            objects.extend(references.objects.iter().cloned());
+            indexes.insert(format!("{}.json", references.asset_index));
+            libraries.extend(references.libraries.iter().cloned());
+        }
+
+        let assets = self.assets_directory();
+        let mut unused = Vec::new();
+        for path in files(&assets.join("objects"))? {
+            if !objects.contains(&name_of(&path)) {
+                unused.push(path);
+            }
+        }
+        for path in files(&assets.join("indexes"))? {
+            if !indexes.contains(&name_of(&path)) {
+                unused.push(path);
+            }
+        }
+        let libraries_directory = self.libraries_directory();
+        for path in files(&libraries_directory)? {
+            let relative = path
+                .strip_prefix(&libraries_directory)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            if !libraries.contains(&relative) {
+                unused.push(path);
+            }
+        }
+
+        let mut report = GcReport {
+            dry_run,
+            ..GcReport::default()
+        };
+        for path in unused {
+            let size = fs::metadata(&path)?.len();
+            if !dry_run {
+                fs::remove_file(&path)?;
+            }
+            report.reclaimable_bytes += size;
+            report.files.push(path);
+        }
+        if !dry_run {
+            remove_empty_directories(&assets.join("objects"));
+            remove_empty_directories(&libraries_directory);
+        }
+        debug!(
+            "STORE GC {} FILES, {} BYTES{}",
+            report.files.len(),
+            report.reclaimable_bytes,
+            if dry_run { " (DRY RUN)" } else { "" }
+        );
+        Ok(report)
+    }
+    /// Waits up to `LOCK_TIMEOUT` for the other launchers using the store
+    fn lock(&self) -> Result<StoreLock, StoreError> {
+        fs::create_dir_all(&self.root)?;
+        let path = self.root.join("store.lock");
+        let start = Instant::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(StoreLock { path }),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    if start.elapsed() > LOCK_TIMEOUT {
+                        return Err(StoreError::Locked(path.display().to_string()));
+                    }
+                    thread::sleep(Duration::from_millis(50));
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+    fn reload(&mut self) -> Result<(), StoreError> {
+        let path = self.root.join("store.json");
+        self.data = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            StoreFile::default()
+        };
+        Ok(())
+    }
+    fn save(&self) -> Result<(), StoreError> {
+        fs::create_dir_all(&self.root)?;
+        let path = self.root.join("store.json");
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(&self.data)?)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+}
+
+/// Lock file of the store, removed when dropped
+struct StoreLock {
+    path: PathBuf,
+}
+impl Drop for StoreLock {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!("REMOVING STORE LOCK {} FAILED: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Paths `LibsUtil::fetch` downloads the library to, every native classifier included
+fn library_paths(library: &Library) -> Vec<String> {
+    match &library.downloads {
+        Some(downloads) => downloads
+            .artifact
+            .iter()
+            .chain(downloads.classifiers.iter().flat_map(|c| c.values()))
+            .map(|artifact| artifact.path.clone())
+            .collect(),
+        None => {
+            if library.name.split(':').count() < 3 {
+                warn!("MALFORMED LIBRARY NAME {}", library.name);
+                return Vec::new();
+            }
+            vec![MavenLibrary::parse(library.name.clone(), library.url.clone()).name()]
+        }
+    }
+}
+
+fn name_of(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Files under the directory without the downloads in progress, none when it doesn't exist
+fn files(directory: &Path) -> Result<Vec<PathBuf>, StoreError> {
+    let mut found = Vec::new();
+    if !directory.is_dir() {
+        return Ok(found);
+    }
+    let mut pending = vec![directory.to_path_buf()];
+    while let Some(directory) = pending.pop() {
+        for entry in fs::read_dir(&directory)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                pending.push(path);
+            } else if path.extension().is_none_or(|ext| ext != "tmp") {
+                found.push(path);
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// Removes the empty directories left under the directory, keeping the directory itself
+fn remove_empty_directories(directory: &Path) {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            remove_empty_directories(&path);
+            // fails while the directory still has files
+            let _ = fs::remove_dir(&path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEPT: &str = "aa11111111111111111111111111111111111111";
+    const UNUSED: &str = "bb22222222222222222222222222222222222222";
+
+    fn store(name: &str) -> SharedStore {
+        let root = std::env::temp_dir().join(format!("mcd-store-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let files = [
+            format!("assets/objects/aa/{}", KEPT),
+            format!("assets/objects/bb/{}", UNUSED),
+            format!("assets/objects/bb/{}.tmp", UNUSED),
+            String::from("assets/indexes/17.json"),
+            String::from("assets/indexes/8.json"),
+            String::from("libraries/org/lwjgl/lwjgl/3.3.3/lwjgl-3.3.3.jar"),
+            String::from("libraries/com/old/old/1.0/old-1.0.jar"),
+        ];
+        for file in files {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "data").unwrap();
+        }
+        let mut store = SharedStore::open(&root.to_string_lossy()).unwrap();
+        let client: Client = serde_json::from_str(
+            r#"{"id":"1.21.1","mainClass":"M","releaseTime":"","time":"","type":"release",
+                "assetIndex":{"id":"17","sha1":"","size":0,"totalSize":0,"url":""},
+                "libraries":[{"name":"org.lwjgl:lwjgl:3.3.3","downloads":{"artifact":{
+                    "path":"org/lwjgl/lwjgl/3.3.3/lwjgl-3.3.3.jar","sha1":"","size":4,"url":""}}}]}"#,
+        )
+        .unwrap();
+        let assets: Assets = serde_json::from_str(&format!(
+            r#"{{"objects":{{"icons/icon_16x16.png":{{"hash":"{}","size":4}}}}}}"#,
+            KEPT
+        ))
+        .unwrap();
+        store.register(&client, &assets).unwrap();
+        store
+    }
+
+    fn relative(store: &SharedStore, files: &[PathBuf]) -> Vec<String> {
+        let mut files: Vec<String> = files
+            .iter()
+            .map(|file| {
+                let file = file.strip_prefix(&store.root).unwrap();
+                file.to_string_lossy().replace('\\', "/")
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    fn unused() -> Vec<String> {
+        vec![
+            String::from("assets/indexes/8.json"),
+            format!("assets/objects/bb/{}", UNUSED),
+            String::from("libraries/com/old/old/1.0/old-1.0.jar"),
+        ]
+    }
+
+    #[test]
+    fn dry_run_only_reports() {
+        let mut store = store("dry");
+        let report = store.gc(true).unwrap();
+        assert!(report.dry_run);
+        assert_eq!(relative(&store, &report.files), unused());
+        assert_eq!(report.reclaimable_bytes, 12);
+        assert!(report.files.iter().all(|file| file.exists()));
+        fs::remove_dir_all(&store.root).unwrap();
+    }
+
+    #[test]
+    fn gc_removes_unused_files() {
+        let mut store = store("gc");
+        let report = store.gc(false).unwrap();
+        assert_eq!(relative(&store, &report.files), unused());
+        assert!(report.files.iter().all(|file| !file.exists()));
+        let root = store.root.clone();
+        assert!(root.join(format!("assets/objects/aa/{}", KEPT)).is_file());
+        assert!(
+            root.join(format!("assets/objects/bb/{}.tmp", UNUSED))
+                .is_file()
+        );
+        assert!(root.join("assets/indexes/17.json").is_file());
+        assert!(
+            root.join("libraries/org/lwjgl/lwjgl/3.3.3/lwjgl-3.3.3.jar")
+                .is_file()
+        );
+        // the empty directories of the removed library go too
+        assert!(!root.join("libraries/com").exists());
+        assert!(!root.join("store.lock").exists());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn gc_refuses_an_empty_store() {
+        let mut store = store("empty");
+        store.unregister("1.21.1").unwrap();
+        assert!(matches!(store.gc(true), Err(StoreError::NoReferences())));
+        assert!(matches!(
+            store.unregister("1.21.1"),
+            Err(StoreError::NotRegistered(_))
+        ));
+        fs::remove_dir_all(&store.root).unwrap();
+    }
+}
@@ -4,43 +4,156 @@ use tracing::debug;
 
 use crate::{api::client::Rule, errors::FillingError, os::system::OperatingSystem};
 
+/// What [`FillingUtil::fill`] does with placeholders that have no value
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FillingMode {
+    /// Fails with every missing key
+    #[default]
+    Strict,
+    /// Keeps the placeholder as written
+    Lenient,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token<'a> {
+    Text(&'a str),
+    Key(&'a str),
+}
+
+/// Splits the text in literal parts and `${key}` placeholders. In a run of `$` before `{`
+/// each `$$` is a literal `$` and a remaining `$` opens a placeholder: `$${a}` is the text
+/// `${a}`, `$$${a}` is `$` then the `a` placeholder. Other `$` are kept as written
+pub fn tokenize(text: &str) -> Result<Vec<Token<'_>>, FillingError> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+    while let Some(brace) = rest.find("${").map(|start| start + 1) {
+        let run = rest[..brace].trim_end_matches('$').len();
+        if run > 0 {
+            tokens.push(Token::Text(&rest[..run]));
+        }
+        let dollars = brace - run;
+        if dollars > 1 {
+            tokens.push(Token::Text(&rest[run..run + dollars / 2]));
+        }
+        if dollars.is_multiple_of(2) {
+            tokens.push(Token::Text("{"));
+            rest = &rest[brace + 1..];
+            continue;
+        }
+        let after = &rest[brace + 1..];
+        let Some(end) = after.find('}') else {
+            return Err(FillingError::Malformed(text.to_owned()));
+        };
+        let key = &after[..end];
+        if key.is_empty() || key.contains("${") {
+            return Err(FillingError::Malformed(text.to_owned()));
+        }
+        tokens.push(Token::Key(key));
+        rest = &after[end + 1..];
+    }
+    if !rest.is_empty() {
+        tokens.push(Token::Text(rest));
+    }
+    Ok(tokens)
+}
+
+/// Keys of the `${key}` placeholders in the text, malformed text has none
+pub fn placeholders(text: &str) -> Vec<String> {
+    tokenize(text)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|token| match token {
+            Token::Key(key) => Some(key.to_owned()),
+            Token::Text(_) => None,
+        })
+        .collect()
+}
+
 pub struct FillingUtil {
     pub data: HashMap<String, String>,
+    pub mode: FillingMode,
+}
+impl Default for FillingUtil {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 impl FillingUtil {
     pub fn new() -> Self {
         Self {
             data: HashMap::new(),
+            mode: FillingMode::Strict,
         }
     }
     pub fn with_data(mut self, data: HashMap<String, String>) -> Self {
         self.data = data;
         self
     }
+    pub fn with_mode(mut self, mode: FillingMode) -> Self {
+        self.mode = mode;
+        self
+    }
     pub fn insert(&mut self, k: &str, v: String) {
         self.data.insert(k.to_owned(), v);
     }
+    /// Replaces the placeholders in a single pass, values are never filled again
     pub fn fill(&self, text: String) -> Result<String, FillingError> {
-        if !text.contains("${") {
-            return Err(FillingError::NoPattron());
-        }
-        let mut text = text;
-        loop {
-            if !text.contains("${") {
-                return Ok(text);
+        let mut result = String::with_capacity(text.len());
+        let mut missing: Vec<String> = Vec::new();
+        for token in tokenize(&text)? {
+            match token {
+                Token::Text(text) => result.push_str(text),
+                Token::Key(key) => match self.data.get(key) {
+                    Some(value) => {
+                        debug!("KEY FILL {}", key);
+                        result.push_str(value);
+                    }
+                    None => {
+                        if !missing.iter().any(|k| k == key) {
+                            missing.push(key.to_owned());
+                        }
+                        result.push_str(&format!("${{{}}}", key));
+                    }
+                },
             }
-            let key_start_index = text.find("{").unwrap() + 1;
-            let key_end_index = text.find("}").unwrap();
-            let key: &str = &text[key_start_index..key_end_index];
-            debug!("KEY FILL {}", key);
-            if !self.data.contains_key(key) {
-                return Err(FillingError::NoKeyFound(key.to_owned()));
+        }
+        if self.mode == FillingMode::Strict && !missing.is_empty() {
+            return Err(FillingError::MissingKeys(missing));
+        }
+        Ok(result)
+    }
+    /// Fills every argument, in strict mode the error lists the missing keys of all of them
+    pub fn fill_all(&self, args: &[String]) -> Result<Vec<String>, FillingError> {
+        let missing = self.missing_keys(args)?;
+        if self.mode == FillingMode::Strict && !missing.is_empty() {
+            return Err(FillingError::MissingKeys(missing));
+        }
+        let lenient = FillingUtil {
+            data: self.data.clone(),
+            mode: FillingMode::Lenient,
+        };
+        args.iter().map(|arg| lenient.fill(arg.clone())).collect()
+    }
+    /// Keys a set of arguments needs, in order of appearance
+    pub fn required_keys(args: &[String]) -> Result<Vec<String>, FillingError> {
+        let mut keys: Vec<String> = Vec::new();
+        for arg in args {
+            for token in tokenize(arg)? {
+                if let Token::Key(key) = token
+                    && !keys.iter().any(|k| k == key)
+                {
+                    keys.push(key.to_owned());
+                }
             }
-            text = text.replace(
-                format!("${{{}}}", key).as_str(),
-                self.data.get(key).unwrap(),
-            );
         }
+        Ok(keys)
+    }
+    /// Required keys of the arguments without a value
+    pub fn missing_keys(&self, args: &[String]) -> Result<Vec<String>, FillingError> {
+        Ok(Self::required_keys(args)?
+            .into_iter()
+            .filter(|key| !self.data.contains_key(key))
+            .collect())
     }
 }
 
@@ -84,3 +197,130 @@ pub fn resolve_rules_feat(rules: &[Rule], options: &HashMap<String, bool>) -> bo
     }
     true
 }
+
+/// Matches a `/` separated path against a glob: `*` and `?` stay inside a segment,
+/// `**` spans segments
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    glob_match_bytes(pattern.as_bytes(), text.as_bytes())
+}
+fn glob_match_bytes(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            // `a/**/b` also matches `a/b`
+            if let Some(after) = rest.strip_prefix(b"/")
+                && glob_match_bytes(after, text)
+            {
+                return true;
+            }
+            (0..=text.len()).any(|i| glob_match_bytes(rest, &text[i..]))
+        }
+        [b'*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != b'/')
+            .any(|i| glob_match_bytes(rest, &text[i..])),
+        [b'?', rest @ ..] => {
+            text.first().is_some_and(|c| *c != b'/') && glob_match_bytes(rest, &text[1..])
+        }
+        [c, rest @ ..] => text.first() == Some(c) && glob_match_bytes(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filler(mode: FillingMode) -> FillingUtil {
+        let mut fill = FillingUtil::new().with_mode(mode);
+        fill.insert("a", String::from("X"));
+        fill
+    }
+
+    #[test]
+    fn tokenize_escapes() {
+        assert_eq!(
+            tokenize("$${a}").unwrap(),
+            [Token::Text("$"), Token::Text("{"), Token::Text("a}")]
+        );
+        let fill = filler(FillingMode::Strict);
+        assert_eq!(fill.fill(String::from("${a}")).unwrap(), "X");
+        assert_eq!(fill.fill(String::from("$${a}")).unwrap(), "${a}");
+        assert_eq!(fill.fill(String::from("$$${a}")).unwrap(), "$X");
+        assert_eq!(fill.fill(String::from("$$$${a}")).unwrap(), "$${a}");
+        assert_eq!(fill.fill(String::from("a$b ${a}$")).unwrap(), "a$b X$");
+    }
+
+    #[test]
+    fn tokenize_malformed() {
+        for text in ["${", "${a", "${}", "${a${b}}"] {
+            assert!(
+                matches!(tokenize(text), Err(FillingError::Malformed(_))),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn values_are_not_filled_again() {
+        let mut fill = filler(FillingMode::Strict);
+        fill.insert("b", String::from("${a}"));
+        assert_eq!(fill.fill(String::from("${b}")).unwrap(), "${a}");
+    }
+
+    #[test]
+    fn strict_collects_missing_keys() {
+        let fill = filler(FillingMode::Strict);
+        let Err(FillingError::MissingKeys(keys)) = fill.fill(String::from("${c} ${a} ${b} ${c}"))
+        else {
+            panic!("missing keys not reported");
+        };
+        assert_eq!(keys, ["c", "b"]);
+    }
+
+    #[test]
+    fn lenient_keeps_placeholders() {
+        let fill = filler(FillingMode::Lenient);
+        assert_eq!(fill.fill(String::from("${a} ${b}")).unwrap(), "X ${b}");
+    }
+
+    #[test]
+    fn fill_all_reports_every_argument() {
+        let args = [
+            String::from("${b}"),
+            String::from("${a}"),
+            String::from("${c}${b}"),
+        ];
+        let Err(FillingError::MissingKeys(keys)) = filler(FillingMode::Strict).fill_all(&args)
+        else {
+            panic!("missing keys not reported");
+        };
+        assert_eq!(keys, ["b", "c"]);
+        assert_eq!(
+            filler(FillingMode::Lenient).fill_all(&args).unwrap(),
+            ["${b}", "X", "${c}${b}"]
+        );
+    }
+
+    #[test]
+    fn glob_segments() {
+        assert!(glob_match(
+            "minecraft/sounds/*.ogg",
+            "minecraft/sounds/a.ogg"
+        ));
+        assert!(!glob_match(
+            "minecraft/sounds/*.ogg",
+            "minecraft/sounds/mob/a.ogg"
+        ));
+        assert!(glob_match(
+            "minecraft/sounds/**",
+            "minecraft/sounds/mob/cow/say1.ogg"
+        ));
+        assert!(glob_match("**/*.png", "minecraft/textures/a.png"));
+        assert!(glob_match("a/**/b", "a/b"));
+        assert!(glob_match("a/**/b", "a/x/y/b"));
+        assert!(glob_match("lang/??_??.json", "lang/de_de.json"));
+        assert!(!glob_match("lang/?.json", "lang//.json"));
+        assert!(!glob_match("pack.png", "pack.png.bak"));
+        assert!(glob_match("", ""));
+    }
+}